@@ -1,35 +1,82 @@
-use std::fmt::Write;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
+use ratatui::layout::Rect;
 
-use crate::widgets::{selectable_list::SelectableList, text_input::TextInput};
+use crate::{
+    autosquash::autosquash,
+    config::{Config, MissingCommitsCheck, EXEC_COMMAND_KEY},
+    diff::Diff,
+    fixup_tree::{fixup_groups, group_of_line, shifted_order, with_folded_lines},
+    git::{CommitDetails, Git},
+    history::History,
+    message::{amend_command, parse_amend_command},
+    mouse::Mouse,
+    reorder::predict_conflicts,
+    search::{fuzzy_matches, Search},
+    session::Session,
+    split::{format_plans, group_count, SplitFile, SplitPlan},
+    todo::{
+        detect_comment_char, format_todo_list, parse_todo_list, CommitLine, MessageOption,
+        TodoCommand, TodoItem, TodoLine,
+    },
+    validation::{missing_commits, validate, Problem},
+    vim::Vim,
+    widgets::{selectable_list::SelectableList, text_area::TextArea, text_input::TextInput},
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct RebaseConfirmation(pub bool);
 
-pub struct Command(pub &'static str);
-
-pub const COMMANDS: [Command; 12] = [
-    Command("pick"),
-    Command("reword"),
-    Command("edit"),
-    Command("squash"),
-    Command("fixup"),
-    Command("exec"),
-    Command("break"),
-    Command("drop"),
-    Command("label"),
-    Command("reset"),
-    Command("merge"),
-    Command("update-ref"),
-];
+pub struct Command {
+    pub name: &'static str,
+    pub message_option: Option<MessageOption>,
+}
+
+impl Command {
+    const fn new(name: &'static str) -> Self {
+        Command {
+            name,
+            message_option: None,
+        }
+    }
+
+    const fn with_message_option(name: &'static str, message_option: MessageOption) -> Self {
+        Command {
+            name,
+            message_option: Some(message_option),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.message_option {
+            Some(message_option) => format!("{} {}", self.name, message_option.flag()),
+            None => self.name.to_string(),
+        }
+    }
 
-#[derive(Clone)]
-pub struct TodoItem {
-    pub command: String,
-    pub parameters: String,
+    fn matches(&self, command: &TodoCommand) -> bool {
+        self.name == command.name() && self.message_option == command.message_option()
+    }
 }
 
+pub const COMMANDS: [Command; 14] = [
+    Command::new("pick"),
+    Command::new("reword"),
+    Command::new("edit"),
+    Command::new("squash"),
+    Command::new("fixup"),
+    Command::with_message_option("fixup", MessageOption::Use),
+    Command::with_message_option("fixup", MessageOption::Edit),
+    Command::new("exec"),
+    Command::new("break"),
+    Command::new("drop"),
+    Command::new("label"),
+    Command::new("reset"),
+    Command::new("merge"),
+    Command::new("update-ref"),
+];
+
 pub enum EditingWhat<'a> {
     Command(SelectableList<'a, &'a [Command]>),
     Parameters(TextInput),
@@ -39,73 +86,307 @@ pub enum Mode<'a> {
     Main,
     Editing {
         what: EditingWhat<'a>,
-        original_item: Option<TodoItem>,
     },
     ShowingOriginal {
         scroll: u16,
     },
-    Quitting(SelectableList<'a, [RebaseConfirmation; 2]>),
+    ShowingDiff {
+        diff: Diff,
+        scroll: u16,
+    },
+    ShowingProblems {
+        problems: SelectableList<'a, Vec<Problem>>,
+        /// Only warnings were found when quitting, so the rebase can go on.
+        can_proceed: bool,
+    },
+    EditingMessage {
+        input: TextArea,
+        /// The line of the commit.
+        index: usize,
+    },
+    PlanningSplit {
+        /// The line of the commit.
+        index: usize,
+        files: SelectableList<'a, Vec<SplitFile>>,
+    },
+    InsertingExec {
+        input: TextInput,
+        /// Whether the lines go after the selected commits only, rather than after all of them.
+        selected_only: bool,
+    },
+    RestoringSession {
+        /// The list of the interrupted session.
+        todo_lines: Vec<TodoLine>,
+        scroll: u16,
+    },
+    PreviewingAutosquash {
+        /// The rearranged list, or `None` if there is nothing to rearrange.
+        todo_lines: Option<Vec<TodoLine>>,
+        scroll: u16,
+    },
+    Searching {
+        input: TextInput,
+        filter: bool,
+        /// The selected line when the search started.
+        origin: usize,
+    },
+    Quitting {
+        confirmation: SelectableList<'a, Vec<RebaseConfirmation>>,
+        /// Commits from the original list that were removed without being dropped.
+        missing_commits: Vec<String>,
+    },
+}
+
+/// Where the clickable lists were last drawn, without their borders and padding.
+#[derive(Default)]
+pub struct ScreenAreas {
+    pub todo_list: Rect,
+    pub commands: Rect,
+    pub confirmation: Rect,
+}
+
+struct Snapshot {
+    todo_lines: Vec<TodoLine>,
+    selected: usize,
 }
 
 pub struct App<'a> {
-    pub todo_list: SelectableList<'a, Vec<TodoItem>>,
+    pub todo_list: SelectableList<'a, Vec<TodoLine>>,
     pub page_length: usize,
     pub mode: Mode<'a>,
     pub original_todo_list_lines: Vec<&'a str>,
+    pub config: Config,
+    pub git: Git,
+    pub show_commit_details: bool,
+    /// Whether removing commits turns them into `drop`s, see [`Config::drop_on_remove`].
+    pub drop_on_remove: bool,
+    pub vim: Vim,
+    pub mouse: Mouse,
+    pub screen_areas: ScreenAreas,
+    commit_details: HashMap<String, Option<CommitDetails>>,
+    /// Commits that were moved before other commits touching the same files, mapped to
+    /// those other commits.
+    pub reorder_conflicts: HashMap<String, Vec<String>>,
+    commit_files: HashMap<String, Vec<String>>,
+    commit_authors: HashMap<String, String>,
+    pub search: Option<Search>,
+    /// The plans of the commits marked for splitting, by commit hash.
+    pub split_plans: HashMap<String, SplitPlan>,
+    /// Where the list is saved after every change, if anywhere.
+    pub session: Option<Session>,
+    /// The commits whose fixup group is folded, hiding the lines folded into them.
+    folded: HashSet<String>,
+    comment_char: char,
+    original_todo_lines: Vec<TodoLine>,
+    history: History<Snapshot>,
+    /// The state from before the current edition, recorded in the history if it is confirmed.
+    edition_snapshot: Option<Snapshot>,
+    /// The state from before the current drag, recorded in the history when it ends.
+    drag_snapshot: Option<Snapshot>,
 }
 
 impl<'a> App<'a> {
-    pub fn new(todo_list: &'a str) -> Self {
+    pub fn new(todo_list: &'a str, config: Config, git: Git) -> Self {
         let todo_list_lines: Vec<_> = todo_list.lines().collect();
-        let todo_list_items = parse_todo_list(&todo_list_lines);
+        let comment_char = config
+            .comment_char
+            .unwrap_or_else(|| detect_comment_char(&todo_list_lines));
+        let todo_list_items = parse_todo_list(&todo_list_lines, comment_char);
+        let original_todo_lines = todo_list_items.clone();
 
         App {
             todo_list: SelectableList::new(todo_list_items),
             page_length: 0,
             mode: Mode::Main,
             original_todo_list_lines: todo_list_lines,
+            drop_on_remove: config.drop_on_remove,
+            vim: Vim::default(),
+            mouse: Mouse::default(),
+            screen_areas: ScreenAreas::default(),
+            config,
+            show_commit_details: git.is_repository(),
+            git,
+            commit_details: HashMap::new(),
+            reorder_conflicts: HashMap::new(),
+            commit_files: HashMap::new(),
+            commit_authors: HashMap::new(),
+            search: None,
+            split_plans: HashMap::new(),
+            session: None,
+            folded: HashSet::new(),
+            comment_char,
+            original_todo_lines,
+            history: History::new(),
+            edition_snapshot: None,
+            drag_snapshot: None,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            todo_lines: self.todo_list.items().clone(),
+            selected: self.todo_list.selected(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        *self.todo_list.items_mut() = snapshot.todo_lines;
+        self.todo_list.select(snapshot.selected);
+        self.todo_list.clear_multi_selection();
+        self.update_reorder_conflicts();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.history.undo(self.snapshot()) {
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.history.redo(self.snapshot()) {
+            self.restore(snapshot);
         }
     }
 
     pub fn move_todo_item_up(&mut self) {
-        let selected = self.todo_list.selected();
-        if selected > 0 {
-            self.todo_list.items_mut().swap(selected, selected - 1);
-            self.todo_list.select_up(1);
+        if self.is_filtering() {
+            return;
+        }
+        let snapshot = self.snapshot();
+        if self.shift_selected_items(-1) {
+            self.history.record(snapshot);
+            self.update_reorder_conflicts();
         }
     }
 
     pub fn move_todo_item_down(&mut self) {
-        let selected = self.todo_list.selected();
-        if selected < self.todo_list.items().len() - 1 {
-            self.todo_list.items_mut().swap(selected, selected + 1);
-            self.todo_list.select_down(1);
+        if self.is_filtering() {
+            return;
+        }
+        let snapshot = self.snapshot();
+        if self.shift_selected_items(1) {
+            self.history.record(snapshot);
+            self.update_reorder_conflicts();
         }
     }
 
+    /// Moves the selected lines one position up (-1) or down (1), if they are not at the end
+    /// of the list already. Comment lines keep their places, the other lines move around
+    /// them. The lines folded into a selected commit go with it.
+    fn shift_selected_items(&mut self, delta: isize) -> bool {
+        let positions: Vec<usize> = self
+            .todo_list
+            .items()
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.item().is_some())
+            .map(|(index, _)| index)
+            .collect();
+        let lines: Vec<TodoLine> = positions
+            .iter()
+            .map(|&index| self.todo_list.items()[index].clone())
+            .collect();
+        let selection: Vec<usize> = self
+            .todo_list
+            .multi_selection()
+            .into_iter()
+            .filter_map(|index| positions.binary_search(&index).ok())
+            .collect();
+        if selection.is_empty() {
+            return false;
+        }
+        // The groups as drawn, among the moved lines only
+        let groups: Vec<_> = fixup_groups(self.todo_list.items())
+            .into_iter()
+            .map(|group| {
+                positions.partition_point(|&index| index < group.start)
+                    ..positions.partition_point(|&index| index < group.end)
+            })
+            .collect();
+        let selection = with_folded_lines(&selection, &groups);
+        let Some(order) = shifted_order(lines.len(), &selection, &groups, delta) else {
+            return false;
+        };
+
+        let mut new_positions = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            self.todo_list.items_mut()[positions[new]] = lines[old].clone();
+            new_positions[old] = new;
+        }
+        self.todo_list
+            .remap_multi_selection(|index| match positions.binary_search(&index) {
+                Ok(old) => positions[new_positions[old]],
+                Err(_) => index,
+            });
+        true
+    }
+
+    pub fn start_drag(&mut self) {
+        if !self.is_filtering() {
+            self.drag_snapshot = Some(self.snapshot());
+        }
+    }
+
+    /// Moves the selected lines until the cursor is at `index`.
+    pub fn drag_to(&mut self, index: usize) {
+        if self.drag_snapshot.is_none() {
+            return;
+        }
+        let mut moved = false;
+        // Moving over whole groups may go past the index, so the direction is kept
+        let delta = if index < self.todo_list.selected() {
+            -1
+        } else {
+            1
+        };
+        while (index as isize - self.todo_list.selected() as isize) * delta > 0 {
+            if !self.shift_selected_items(delta) {
+                break;
+            }
+            moved = true;
+        }
+        if moved {
+            self.update_reorder_conflicts();
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        if let Some(snapshot) = self.drag_snapshot.take() {
+            if snapshot.todo_lines != *self.todo_list.items() {
+                self.history.record(snapshot);
+            }
+        }
+    }
+
+    fn update_reorder_conflicts(&mut self) {
+        let current_commits = applied_commits(self.todo_list.items());
+        ensure_commit_files(&self.git, &mut self.commit_files, &current_commits);
+
+        let original_commits = applied_commits(&self.original_todo_lines);
+        self.reorder_conflicts = predict_conflicts(&original_commits, &current_commits, |hash| {
+            self.commit_files.get(hash).map(Vec::as_slice)
+        });
+    }
+
     pub fn enter_edition(&mut self) {
-        let original_item = self.todo_list.selected_item().clone();
-        let command = original_item.command.clone();
-        self.mode = Self::make_command_edition_mode(command, Some(original_item));
+        if let TodoLine::Item(item) = self.todo_list.selected_item() {
+            self.mode = Self::make_command_edition_mode(&item.command);
+            self.edition_snapshot = Some(self.snapshot());
+        }
     }
 
     pub fn switch_edition(&mut self) {
-        if let Mode::Editing {
-            what,
-            original_item,
-        } = &mut self.mode
-        {
+        if let Mode::Editing { what } = &mut self.mode {
             match what {
                 EditingWhat::Command(command) => {
                     Self::apply_edited_command(&mut self.todo_list, command);
-                    let parameters = self.todo_list.selected_item().parameters.clone();
-                    self.mode =
-                        Self::make_parameters_edition_mode(parameters, original_item.clone());
+                    let parameters = selected_todo_item(&mut self.todo_list).command.parameters();
+                    self.mode = Self::make_parameters_edition_mode(parameters);
                 }
                 EditingWhat::Parameters(parameters) => {
                     Self::apply_edited_parameters(&mut self.todo_list, parameters);
-                    let command = self.todo_list.selected_item().command.clone();
-                    self.mode = Self::make_command_edition_mode(command, original_item.clone());
+                    let command = &selected_todo_item(&mut self.todo_list).command;
+                    self.mode = Self::make_command_edition_mode(command);
                 }
             }
         } else {
@@ -124,61 +405,68 @@ impl<'a> App<'a> {
                 }
             }
 
+            if let Some(snapshot) = self.edition_snapshot.take() {
+                if snapshot.todo_lines != *self.todo_list.items() {
+                    self.history.record(snapshot);
+                    self.update_reorder_conflicts();
+                }
+            }
             self.mode = Mode::Main;
         } else {
             unimplemented!()
         }
     }
 
-    fn make_command_edition_mode(command: String, original_item: Option<TodoItem>) -> Mode<'a> {
+    fn make_command_edition_mode(command: &TodoCommand) -> Mode<'a> {
         let selected_command_index = COMMANDS
             .iter()
-            .position(|cmd| cmd.0 == command)
+            .position(|cmd| cmd.matches(command))
             .unwrap_or(0);
 
         Mode::Editing {
             what: EditingWhat::Command(
                 SelectableList::new(COMMANDS.as_slice()).with_selected(selected_command_index),
             ),
-            original_item,
         }
     }
 
-    fn make_parameters_edition_mode(
-        parameters: String,
-        original_item: Option<TodoItem>,
-    ) -> Mode<'a> {
+    fn make_parameters_edition_mode(parameters: String) -> Mode<'a> {
         Mode::Editing {
             what: EditingWhat::Parameters(TextInput::new(parameters.chars())),
-            original_item,
         }
     }
 
     fn apply_edited_command(
-        todo_list: &mut SelectableList<Vec<TodoItem>>,
+        todo_list: &mut SelectableList<Vec<TodoLine>>,
         command: &mut SelectableList<'_, &[Command]>,
     ) {
-        todo_list.selected_item_mut().command = command.selected_item().0.to_string();
+        let command = command.selected_item();
+        for index in todo_list.multi_selection() {
+            if let TodoLine::Item(item) = &mut todo_list.items_mut()[index] {
+                item.command = TodoCommand::parse(command.name, &item.command.parameters());
+                item.command.set_message_option(command.message_option);
+            }
+        }
     }
 
     fn apply_edited_parameters(
-        todo_list: &mut SelectableList<'_, Vec<TodoItem>>,
+        todo_list: &mut SelectableList<'_, Vec<TodoLine>>,
         parameters: &TextInput,
     ) {
-        todo_list.selected_item_mut().parameters = parameters.content().iter().collect::<String>();
+        let item = selected_todo_item(todo_list);
+        let parameters = parameters.content().iter().collect::<String>();
+        let message_option = item.command.message_option();
+        item.command = TodoCommand::parse(item.command.name(), &parameters);
+        if item.command.message_option().is_none() {
+            item.command.set_message_option(message_option);
+        }
     }
 
     pub fn cancel_edition(&mut self) {
-        if let Mode::Editing { original_item, .. } = &mut self.mode {
-            match original_item {
-                Some(original_item) => {
-                    let item = self.todo_list.selected_item_mut();
-                    std::mem::swap(item, original_item);
-                }
-                None => {
-                    let index = self.todo_list.selected();
-                    self.todo_list.items_mut().remove(index);
-                }
+        if let Mode::Editing { .. } = self.mode {
+            if let Some(snapshot) = self.edition_snapshot.take() {
+                *self.todo_list.items_mut() = snapshot.todo_lines;
+                self.todo_list.select(snapshot.selected);
             }
             self.mode = Mode::Main;
         } else {
@@ -187,38 +475,378 @@ impl<'a> App<'a> {
     }
 
     pub fn ask_rebase_confirmation(&mut self) {
-        self.mode = Mode::Quitting(SelectableList::new([
-            RebaseConfirmation(true),
-            RebaseConfirmation(false),
-        ]));
+        let problems = validate(self.todo_list.items());
+        if !problems.is_empty() {
+            let can_proceed = problems.iter().all(|problem| problem.warning);
+            self.show_problems(problems, can_proceed);
+            return;
+        }
+        self.confirm_rebase();
+    }
+
+    /// Asks whether to rebase, past the warnings if there are any.
+    pub fn confirm_rebase(&mut self) {
+        let missing_commits = match self.config.missing_commits_check {
+            MissingCommitsCheck::Ignore => Vec::new(),
+            _ => missing_commits(&self.original_todo_lines, self.todo_list.items()),
+        };
+        let confirmation = if self.is_rebase_blocked(&missing_commits) {
+            vec![RebaseConfirmation(false)]
+        } else {
+            vec![RebaseConfirmation(true), RebaseConfirmation(false)]
+        };
+
+        self.mode = Mode::Quitting {
+            confirmation: SelectableList::new(confirmation),
+            missing_commits,
+        };
+    }
+
+    pub fn is_rebase_blocked(&self, missing_commits: &[String]) -> bool {
+        self.config.missing_commits_check == MissingCommitsCheck::Error
+            && !missing_commits.is_empty()
     }
 
     pub fn insert_todo_item(&mut self) {
         let index = self.todo_list.selected();
-        let command = COMMANDS[0].0.to_string();
-        self.todo_list.items_mut().insert(
-            index,
-            TodoItem {
-                command: command.clone(),
-                parameters: String::new(),
-            },
-        );
-        self.mode = Self::make_command_edition_mode(command, None);
+        let command = TodoCommand::parse(COMMANDS[0].name, "");
+        self.mode = Self::make_command_edition_mode(&command);
+        self.edition_snapshot = Some(self.snapshot());
+        self.todo_list.clear_multi_selection();
+        self.todo_list
+            .items_mut()
+            .insert(index, TodoLine::Item(TodoItem::new(command)));
+    }
+
+    /// The selected lines that are not comments.
+    fn selected_item_indices(&self) -> Vec<usize> {
+        self.todo_list
+            .multi_selection()
+            .into_iter()
+            .filter(|index| self.todo_list.items()[*index].item().is_some())
+            .collect()
     }
 
     pub fn remove_todo_item(&mut self) {
-        let index = self.todo_list.selected();
-        self.todo_list.items_mut().remove(index);
-        if index == self.todo_list.items().len() {
-            self.todo_list.select_up(1);
+        let indices = self.selected_item_indices();
+        if indices.is_empty() {
+            return;
         }
+
+        if self.drop_on_remove {
+            self.toggle_drop(&indices);
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        for index in indices.iter().rev() {
+            self.todo_list.items_mut().remove(*index);
+        }
+        self.todo_list.clear_multi_selection();
+        let len = self.todo_list.items().len();
+        self.todo_list.select(indices[0].min(len.saturating_sub(1)));
+        self.update_reorder_conflicts();
+    }
+
+    /// Drops the given items, or restores them if they are all dropped already. Items
+    /// without a commit cannot be dropped and are removed.
+    fn toggle_drop(&mut self, indices: &[usize]) {
+        let snapshot = self.snapshot();
+        let mut commit_items = indices
+            .iter()
+            .filter_map(|index| self.todo_list.items()[*index].item())
+            .filter(|item| item.command.commit().is_some())
+            .peekable();
+        let restore = commit_items.peek().is_some() && commit_items.all(TodoItem::is_dropped);
+        let mut removed = false;
+        for index in indices.iter().rev() {
+            let TodoLine::Item(item) = &mut self.todo_list.items_mut()[*index] else {
+                continue;
+            };
+            if item.is_dropped() == restore && !item.toggle_drop() {
+                self.todo_list.items_mut().remove(*index);
+                removed = true;
+            }
+        }
+        if removed {
+            self.todo_list.clear_multi_selection();
+            let len = self.todo_list.items().len();
+            self.todo_list.select(indices[0].min(len.saturating_sub(1)));
+        }
+        if snapshot.todo_lines != *self.todo_list.items() {
+            self.history.record(snapshot);
+            self.update_reorder_conflicts();
+        }
+    }
+
+    pub fn toggle_drop_on_remove(&mut self) {
+        self.drop_on_remove = !self.drop_on_remove;
     }
 
+    /// Inserts copies of the selected lines after the last of them, and selects the copies.
     pub fn duplicate_todo_item(&mut self) {
-        let item = self.todo_list.selected_item().clone();
+        let indices = self.selected_item_indices();
+        if indices.is_empty() {
+            return;
+        }
+
+        let copies = self.selected_todo_lines();
+        self.insert_todo_lines(indices[indices.len() - 1] + 1, copies);
+    }
+
+    /// Copies of the selected lines that are not comments.
+    pub fn selected_todo_lines(&self) -> Vec<TodoLine> {
+        self.selected_item_indices()
+            .iter()
+            .map(|index| self.todo_list.items()[*index].clone())
+            .collect()
+    }
+
+    /// Inserts the lines after the cursor, and selects them.
+    pub fn paste_todo_lines(&mut self, lines: Vec<TodoLine>) {
+        let position = if self.todo_list.items().is_empty() {
+            0
+        } else {
+            self.todo_list.selected() + 1
+        };
+        self.insert_todo_lines(position, lines);
+    }
+
+    fn insert_todo_lines(&mut self, position: usize, lines: Vec<TodoLine>) {
+        if lines.is_empty() {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        let count = lines.len();
+        self.todo_list.items_mut().splice(position..position, lines);
+        self.todo_list.clear_multi_selection();
+        if count > 1 {
+            self.todo_list.set_marks(position..position + count);
+        }
+        self.todo_list.select(position + count - 1);
+        self.update_reorder_conflicts();
+    }
+
+    /// Inserts an `exec` line running `command` after each of the commits at `indices` that
+    /// are picked, reworded, edited or merged, like `git rebase --exec`: the line goes after
+    /// the squashes and fixups that follow the commit, and is not repeated if it is there
+    /// already.
+    pub fn insert_exec_after(&mut self, indices: &[usize], command: &str) {
+        let items = self.todo_list.items();
+        let is_folded = |index: usize| {
+            items
+                .get(index)
+                .and_then(TodoLine::item)
+                .is_some_and(|item| {
+                    matches!(
+                        item.command,
+                        TodoCommand::Squash(_) | TodoCommand::Fixup(..)
+                    )
+                })
+        };
+        let exec = TodoCommand::Exec(command.to_string());
+
+        let mut positions: Vec<_> = indices
+            .iter()
+            .filter(|index| {
+                items[**index].item().is_some_and(|item| {
+                    matches!(
+                        item.command,
+                        TodoCommand::Pick(_)
+                            | TodoCommand::Reword(_)
+                            | TodoCommand::Edit(_)
+                            | TodoCommand::Merge { .. }
+                    )
+                })
+            })
+            .map(|index| {
+                let mut position = index + 1;
+                while is_folded(position) {
+                    position += 1;
+                }
+                position
+            })
+            .filter(|position| {
+                let next = items.get(*position).and_then(TodoLine::item);
+                next.is_none_or(|item| item.command != exec)
+            })
+            .collect();
+        positions.dedup();
+        if positions.is_empty() {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        let selected = self.todo_list.selected();
+        for position in positions.iter().rev() {
+            self.todo_list
+                .items_mut()
+                .insert(*position, TodoLine::Item(TodoItem::new(exec.clone())));
+        }
+        self.todo_list.clear_multi_selection();
+        let inserted_before = positions.iter().filter(|p| **p <= selected).count();
+        self.todo_list.select(selected + inserted_before);
+    }
+
+    /// Opens the plan of the selected commit, to choose which of its files go to which new
+    /// commit. A commit with a single file is only turned into an `edit`.
+    pub fn start_split_planning(&mut self) {
         let index = self.todo_list.selected();
-        self.todo_list.items_mut().insert(index, item);
-        self.todo_list.select_down(1);
+        let Some(item) = self.todo_list.items()[index].item() else {
+            return;
+        };
+        let (TodoCommand::Pick(commit) | TodoCommand::Reword(commit) | TodoCommand::Edit(commit)) =
+            &item.command
+        else {
+            return;
+        };
+        if commit.hash.is_empty() {
+            return;
+        }
+        let hash = commit.hash.clone();
+
+        let files = match self.split_plans.get(&hash) {
+            Some(plan) => plan.clone(),
+            None => {
+                ensure_commit_files(&self.git, &mut self.commit_files, &[&hash]);
+                self.commit_files[&hash]
+                    .iter()
+                    .map(|path| SplitFile {
+                        path: path.clone(),
+                        group: 1,
+                    })
+                    .collect()
+            }
+        };
+        if files.len() < 2 {
+            self.set_command_at(index, "edit");
+            return;
+        }
+
+        self.mode = Mode::PlanningSplit {
+            index,
+            files: SelectableList::new(files),
+        };
+    }
+
+    /// Saves the plan if it makes several commits, and turns the commit into an `edit`.
+    pub fn confirm_split_plan(&mut self) {
+        let Mode::PlanningSplit { index, files } = std::mem::replace(&mut self.mode, Mode::Main)
+        else {
+            return;
+        };
+        let plan = files.items().clone();
+        if group_count(&plan) < 2 {
+            return;
+        }
+
+        let item = self.todo_list.items()[index].item();
+        let Some(commit) = item.and_then(|item| item.command.commit()) else {
+            return;
+        };
+        self.split_plans.insert(commit.hash.clone(), plan);
+        self.set_command_at(index, "edit");
+    }
+
+    /// The plans of the commits still marked for splitting, to save for the `split`
+    /// subcommand.
+    pub fn get_split_plans_string(&self) -> Result<Option<String>> {
+        let plans: Vec<_> = self
+            .todo_list
+            .items()
+            .iter()
+            .filter_map(TodoLine::item)
+            .filter_map(|item| match &item.command {
+                TodoCommand::Edit(commit) => {
+                    let plan = self.split_plans.get(&commit.hash)?;
+                    Some((commit.hash.as_str(), plan))
+                }
+                _ => None,
+            })
+            .collect();
+        if plans.is_empty() {
+            return Ok(None);
+        }
+        format_plans(plans).map(Some)
+    }
+
+    fn set_command_at(&mut self, index: usize, name: &str) {
+        let Some(item) = self.todo_list.items()[index].item() else {
+            return;
+        };
+        if item.command.name() == name {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        if let TodoLine::Item(item) = &mut self.todo_list.items_mut()[index] {
+            item.command = TodoCommand::parse(name, &item.command.parameters());
+        }
+        self.update_reorder_conflicts();
+    }
+
+    /// Asks for the command of the `exec` lines to insert after the selected commits, or
+    /// after all of them if there is no multi-selection.
+    pub fn start_exec_insertion(&mut self) {
+        let command = self.config.exec_command.as_deref().unwrap_or_default();
+        self.mode = Mode::InsertingExec {
+            input: TextInput::new(command.chars()),
+            selected_only: self.todo_list.has_multi_selection(),
+        };
+    }
+
+    pub fn confirm_exec_insertion(&mut self) {
+        let Mode::InsertingExec {
+            input,
+            selected_only,
+        } = std::mem::replace(&mut self.mode, Mode::Main)
+        else {
+            return;
+        };
+        let command: String = input.content().iter().collect();
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+
+        let indices = if selected_only {
+            self.selected_item_indices()
+        } else {
+            (0..self.todo_list.items().len()).collect()
+        };
+        self.insert_exec_after(&indices, command);
+
+        if self.config.exec_command.as_deref() != Some(command) {
+            // Not being able to remember it is not worth an error
+            self.git.set_config(EXEC_COMMAND_KEY, command);
+            self.config.exec_command = Some(command.to_string());
+        }
+    }
+
+    /// Changes the command of the selected commits, keeping their parameters.
+    pub fn set_command(&mut self, name: &str) {
+        let indices: Vec<_> = self
+            .selected_item_indices()
+            .into_iter()
+            .filter(|index| {
+                let item = self.todo_list.items()[*index].item();
+                item.is_some_and(|item| {
+                    item.command.commit().is_some() && item.command.name() != name
+                })
+            })
+            .collect();
+        if indices.is_empty() {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        for index in indices {
+            if let TodoLine::Item(item) = &mut self.todo_list.items_mut()[index] {
+                item.command = TodoCommand::parse(name, &item.command.parameters());
+            }
+        }
+        self.update_reorder_conflicts();
     }
 
     pub fn select_command_by_char(command: &mut SelectableList<'_, &[Command]>, char: char) {
@@ -229,8 +857,8 @@ impl<'a> App<'a> {
 
         let mut found = None;
 
-        for (i, Command(cmd)) in command.items().iter().enumerate() {
-            if let Some(p) = cmd.chars().position(|ch| ch == char) {
+        for (i, Command { name, .. }) in command.items().iter().enumerate() {
+            if let Some(p) = name.chars().position(|ch| ch == char) {
                 if found.is_none() || matches!(found, Some(Found { char_pos, .. }) if p < char_pos)
                 {
                     found = Some(Found {
@@ -250,34 +878,853 @@ impl<'a> App<'a> {
         }
     }
 
+    pub fn toggle_commit_details(&mut self) {
+        self.show_commit_details = !self.show_commit_details;
+    }
+
+    pub fn selected_commit_details(&mut self) -> Option<&CommitDetails> {
+        if self.todo_list.items().is_empty() {
+            return None;
+        }
+
+        let hash = self
+            .todo_list
+            .selected_item()
+            .item()?
+            .command
+            .commit()?
+            .hash
+            .clone();
+        self.commit_details(&hash)
+    }
+
+    fn commit_details(&mut self, hash: &str) -> Option<&CommitDetails> {
+        if hash.is_empty() {
+            return None;
+        }
+
+        let git = &self.git;
+        self.commit_details
+            .entry(hash.to_string())
+            .or_insert_with(|| git.commit_details(hash))
+            .as_ref()
+    }
+
+    /// The full message of the commit, or its subject if it cannot be read.
+    fn commit_message(&mut self, commit: &CommitLine) -> String {
+        match self.commit_details(&commit.hash) {
+            Some(details) => details.message.clone(),
+            None => commit.subject.clone(),
+        }
+    }
+
+    /// The `exec` line setting the message written ahead of time for the commit at `index`,
+    /// which ends its fixup group, and the message.
+    fn amended_message(&self, index: usize) -> Option<(usize, String)> {
+        let groups = fixup_groups(self.todo_list.items());
+        let last = group_of_line(&groups, index)?.end - 1;
+        match &self.todo_list.items()[last].item()?.command {
+            TodoCommand::Exec(command) => Some((last, parse_amend_command(command)?)),
+            _ => None,
+        }
+    }
+
+    /// Opens the editor on the new message of the selected `reword` or `squash` commit.
+    /// Those commits are then turned into a `pick` or a `fixup` followed by an `exec` line
+    /// setting the message.
+    pub fn start_message_edition(&mut self) {
+        let index = self.todo_list.selected();
+        let Some(item) = self.todo_list.items()[index].item() else {
+            return;
+        };
+        let command = item.command.clone();
+
+        let message = match (&command, self.amended_message(index)) {
+            (_, Some((_, message))) => message,
+            (TodoCommand::Reword(commit), None) => self.commit_message(commit),
+            (TodoCommand::Squash(commit), None) => {
+                // The message of the commit it is squashed into comes first, as with git
+                let target = self.todo_list.items()[..index]
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter_map(|(index, line)| {
+                        let command = &line.item()?.command;
+                        Some((index, command, command.commit()?))
+                    })
+                    .find(|(_, command, _)| {
+                        !matches!(command, TodoCommand::Squash(_) | TodoCommand::Fixup(..))
+                    })
+                    .map(|(_, _, commit)| commit.clone());
+                let mut message = match target {
+                    Some(target) => self.commit_message(&target) + "\n\n",
+                    None => String::new(),
+                };
+                message.push_str(&self.commit_message(commit));
+                message
+            }
+            _ => return,
+        };
+
+        self.mode = Mode::EditingMessage {
+            input: TextArea::new(&message),
+            index,
+        };
+    }
+
+    /// Saves the edited message. An empty message gives back the `reword` or `squash`
+    /// command, for git to ask for the message.
+    pub fn confirm_message_edition(&mut self) {
+        let Mode::EditingMessage { input, index } = std::mem::replace(&mut self.mode, Mode::Main)
+        else {
+            return;
+        };
+        let message = input.content().trim_end().to_string();
+        let previous = self.amended_message(index);
+        if previous.as_ref().map_or("", |(_, message)| message) == message {
+            return;
+        }
+        // Set after the lines folded into the commit, so that the message is the final one
+        let groups = fixup_groups(self.todo_list.items());
+        let group_end = group_of_line(&groups, index).map_or(index + 1, |group| group.end);
+
+        self.history.record(self.snapshot());
+        let items = self.todo_list.items_mut();
+        if let TodoLine::Item(item) = &mut items[index] {
+            let name = match (&item.command, message.is_empty()) {
+                (TodoCommand::Reword(_), false) => Some("pick"),
+                (TodoCommand::Squash(_), false) => Some("fixup"),
+                (TodoCommand::Pick(_), true) => Some("reword"),
+                (TodoCommand::Fixup(None, _), true) => Some("squash"),
+                _ => None,
+            };
+            if let Some(name) = name {
+                item.command = TodoCommand::parse(name, &item.command.parameters());
+            }
+        }
+
+        let exec = TodoLine::Item(TodoItem::new(TodoCommand::Exec(amend_command(&message))));
+        match (previous, message.is_empty()) {
+            (Some((line, _)), true) => {
+                items.remove(line);
+            }
+            (Some((line, _)), false) => items[line] = exec,
+            (None, _) => items.insert(group_end, exec),
+        }
+        self.todo_list.clear_multi_selection();
+    }
+
+    pub fn show_diff(&mut self) {
+        if self.todo_list.items().is_empty() {
+            return;
+        }
+
+        let Some(commit) = self
+            .todo_list
+            .selected_item()
+            .item()
+            .and_then(|item| item.command.commit())
+        else {
+            return;
+        };
+
+        if let Some(diff) = self.load_diff(commit.hash.clone(), false) {
+            self.mode = Mode::ShowingDiff { diff, scroll: 0 };
+        }
+    }
+
+    pub fn toggle_word_diff(&mut self) {
+        if let Mode::ShowingDiff { diff, scroll } = &self.mode {
+            let file_index = diff.file_index(*scroll as usize);
+            if let Some(new_diff) = self.load_diff(diff.commit.clone(), !diff.word_diff) {
+                let scroll = file_index
+                    .and_then(|index| new_diff.file_position(index))
+                    .unwrap_or(0);
+                self.mode = Mode::ShowingDiff {
+                    diff: new_diff,
+                    scroll: scroll as u16,
+                };
+            }
+        } else {
+            unimplemented!()
+        }
+    }
+
+    fn load_diff(&self, commit: String, word_diff: bool) -> Option<Diff> {
+        if commit.is_empty() {
+            return None;
+        }
+
+        let text = self.git.commit_diff(&commit, word_diff)?;
+        Some(Diff::new(commit, word_diff, &text))
+    }
+
+    pub fn start_search(&mut self, filter: bool) {
+        self.load_search_data();
+        let query = match &self.search {
+            Some(search) if search.filter == filter => search.query.as_str(),
+            _ => "",
+        };
+        self.mode = Mode::Searching {
+            input: TextInput::new(query.chars()),
+            filter,
+            origin: self.todo_list.selected(),
+        };
+        self.update_search();
+    }
+
+    /// Applies the query being typed, and selects its first match from where the search
+    /// started.
+    pub fn update_search(&mut self) {
+        let Mode::Searching {
+            input,
+            filter,
+            origin,
+        } = &self.mode
+        else {
+            return;
+        };
+        let query: String = input.content().iter().collect();
+        let (filter, origin) = (*filter, *origin);
+
+        self.search = (!query.trim().is_empty()).then_some(Search { query, filter });
+        self.todo_list.select(origin);
+        if let Some(index) = self.matching_indices().into_iter().find(|i| *i >= origin) {
+            self.todo_list.select(index);
+        }
+    }
+
+    pub fn confirm_search(&mut self) {
+        self.mode = Mode::Main;
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Mode::Searching { origin, .. } = self.mode {
+            self.search = None;
+            self.todo_list.select(origin);
+            self.mode = Mode::Main;
+        }
+    }
+
+    pub fn is_filtering(&self) -> bool {
+        self.search.as_ref().is_some_and(|search| search.filter)
+    }
+
+    /// The indices of the lines matching the search, in order. All of them if there is no
+    /// search.
+    pub fn matching_indices(&self) -> Vec<usize> {
+        let lines = self.todo_list.items().iter().enumerate();
+        match &self.search {
+            Some(search) => lines
+                .filter(|(_, line)| self.matches(&search.query, line))
+                .map(|(index, _)| index)
+                .collect(),
+            None => lines.map(|(index, _)| index).collect(),
+        }
+    }
+
+    fn matches(&self, query: &str, line: &TodoLine) -> bool {
+        let Some(item) = line.item() else {
+            return false;
+        };
+        let parameters = item.command.parameters();
+        let hash = item.command.commit().map(|commit| commit.hash.as_str());
+        let author = hash.and_then(|hash| self.commit_authors.get(hash));
+        let files = hash.and_then(|hash| self.commit_files.get(hash));
+
+        let fields = std::iter::once(parameters.as_str())
+            .chain(author.map(String::as_str))
+            .chain(files.into_iter().flatten().map(String::as_str));
+        fuzzy_matches(query, fields)
+    }
+
+    pub fn next_match(&mut self) {
+        let selected = self.todo_list.selected();
+        let matches = self.matching_indices();
+        let next = matches.iter().find(|index| **index > selected);
+        if let Some(index) = next.or(matches.first()) {
+            self.todo_list.select(*index);
+        }
+    }
+
+    pub fn previous_match(&mut self) {
+        let selected = self.todo_list.selected();
+        let matches = self.matching_indices();
+        let previous = matches.iter().rev().find(|index| **index < selected);
+        if let Some(index) = previous.or(matches.last()) {
+            self.todo_list.select(*index);
+        }
+    }
+
+    /// Hides the lines that do not match the filter, if any, and those of folded groups.
+    pub fn update_shown_lines(&mut self) {
+        let folded_lines = self.folded_lines();
+        let shown = self.is_filtering().then(|| {
+            let mut shown = self.matching_indices();
+            // The line being edited stays visible even if it does not match anymore
+            if let Mode::Editing { .. } = self.mode {
+                let selected = self.todo_list.selected();
+                if let Err(position) = shown.binary_search(&selected) {
+                    shown.insert(position, selected);
+                }
+            }
+            shown
+        });
+        let shown = shown
+            .or_else(|| {
+                (!folded_lines.is_empty()).then(|| (0..self.todo_list.items().len()).collect())
+            })
+            .map(|shown| {
+                shown
+                    .into_iter()
+                    .filter(|index| !folded_lines.contains(index))
+                    .collect()
+            });
+        self.todo_list.set_shown(shown);
+    }
+
+    /// Whether the line is a commit whose fixup group is folded.
+    pub fn is_folded(&self, index: usize) -> bool {
+        self.todo_list.items()[index]
+            .item()
+            .and_then(|item| item.command.commit())
+            .is_some_and(|commit| self.folded.contains(&commit.hash))
+    }
+
+    /// The lines hidden in folded groups.
+    fn folded_lines(&self) -> HashSet<usize> {
+        fixup_groups(self.todo_list.items())
+            .into_iter()
+            .filter(|group| self.is_folded(group.start))
+            .flat_map(|group| group.start + 1..group.end)
+            .collect()
+    }
+
+    /// Folds or unfolds the group of the selected line, selecting the commit the other lines
+    /// are folded into.
+    pub fn toggle_fold(&mut self) {
+        let groups = fixup_groups(self.todo_list.items());
+        let Some(parent) = group_of_line(&groups, self.todo_list.selected()).map(|g| g.start)
+        else {
+            return;
+        };
+        let Some(commit) = self.todo_list.items()[parent]
+            .item()
+            .and_then(|item| item.command.commit())
+        else {
+            return;
+        };
+        if !self.folded.remove(&commit.hash) {
+            self.folded.insert(commit.hash.clone());
+        }
+        self.todo_list.select(parent);
+    }
+
+    /// Loads the authors and changed files of the commits, which the search also looks into.
+    fn load_search_data(&mut self) {
+        let commits: Vec<_> = self
+            .todo_list
+            .items()
+            .iter()
+            .filter_map(|line| line.item()?.command.commit())
+            .map(|commit| commit.hash.as_str())
+            .filter(|hash| !hash.is_empty() && !self.commit_authors.contains_key(*hash))
+            .collect();
+        if commits.is_empty() {
+            return;
+        }
+
+        let mut authors = self.git.commits_authors(&commits);
+        ensure_commit_files(&self.git, &mut self.commit_files, &commits);
+        for hash in commits {
+            // Unresolvable commits are cached with no author, so they are not queried again
+            let author = authors.remove(hash).unwrap_or_default();
+            self.commit_authors.insert(hash.to_string(), author);
+        }
+    }
+
+    pub fn preview_autosquash(&mut self) {
+        self.mode = Mode::PreviewingAutosquash {
+            todo_lines: autosquash(self.todo_list.items()),
+            scroll: 0,
+        };
+    }
+
+    /// Replaces the list with the previewed arrangement, keeping the cursor on the same line.
+    pub fn apply_autosquash(&mut self) {
+        let Mode::PreviewingAutosquash { todo_lines, .. } =
+            std::mem::replace(&mut self.mode, Mode::Main)
+        else {
+            return;
+        };
+        let Some(todo_lines) = todo_lines else {
+            return;
+        };
+
+        self.history.record(self.snapshot());
+        let selected = self.todo_list.selected_item().clone();
+        let position = todo_lines
+            .iter()
+            .position(|line| match (line.item(), selected.item()) {
+                // The command may have changed
+                (Some(item), Some(selected)) => {
+                    item.command.parameters() == selected.command.parameters()
+                }
+                _ => *line == selected,
+            })
+            .unwrap_or_default();
+        *self.todo_list.items_mut() = todo_lines;
+        self.todo_list.select(position);
+        self.todo_list.clear_multi_selection();
+        self.update_reorder_conflicts();
+    }
+
+    /// Offers to go back to the list of an interrupted session.
+    pub fn offer_session_restore(&mut self, todo_list: &str) {
+        let todo_list_lines: Vec<_> = todo_list.lines().collect();
+        self.mode = Mode::RestoringSession {
+            todo_lines: parse_todo_list(&todo_list_lines, self.comment_char),
+            scroll: 0,
+        };
+    }
+
+    pub fn restore_session(&mut self) {
+        let Mode::RestoringSession { todo_lines, .. } =
+            std::mem::replace(&mut self.mode, Mode::Main)
+        else {
+            return;
+        };
+
+        self.history.record(self.snapshot());
+        *self.todo_list.items_mut() = todo_lines;
+        self.todo_list.select(0);
+        self.todo_list.clear_multi_selection();
+        self.update_reorder_conflicts();
+    }
+
+    /// Replaces the list with one edited as text, showing its problems if there are any.
+    pub fn replace_todo_list(&mut self, todo_list: &str) -> Result<()> {
+        if todo_list == self.get_todo_list_string()? {
+            return Ok(());
+        }
+
+        let todo_list_lines: Vec<_> = todo_list.lines().collect();
+        let todo_lines = parse_todo_list(&todo_list_lines, self.comment_char);
+        self.history.record(self.snapshot());
+        let selected = self
+            .todo_list
+            .selected()
+            .min(todo_lines.len().saturating_sub(1));
+        *self.todo_list.items_mut() = todo_lines;
+        self.todo_list.select(selected);
+        self.todo_list.clear_multi_selection();
+        self.update_reorder_conflicts();
+
+        let problems = validate(self.todo_list.items());
+        if !problems.is_empty() {
+            self.show_problems(problems, false);
+        }
+        Ok(())
+    }
+
+    fn show_problems(&mut self, problems: Vec<Problem>, can_proceed: bool) {
+        self.todo_list.select(problems[0].index);
+        self.mode = Mode::ShowingProblems {
+            problems: SelectableList::new(problems),
+            can_proceed,
+        };
+    }
+
+    pub fn save_session(&mut self) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if let Ok(todo_list) =
+            format_todo_list(self.todo_list.items(), self.config.abbreviate_commands)
+        {
+            // Not being able to save the session is not worth interrupting the edition
+            let _ = session.save(&todo_list);
+        }
+    }
+
     pub fn show_original_todo_list(&mut self) {
         self.mode = Mode::ShowingOriginal { scroll: 0 };
     }
 
     pub fn get_todo_list_string(&self) -> Result<String> {
-        format_todo_list(self.todo_list.items())
+        format_todo_list(self.todo_list.items(), self.config.abbreviate_commands)
+    }
+}
+
+/// Must be called only while editing, which is never entered on a comment line.
+fn selected_todo_item<'l>(
+    todo_list: &'l mut SelectableList<'_, Vec<TodoLine>>,
+) -> &'l mut TodoItem {
+    match todo_list.selected_item_mut() {
+        TodoLine::Item(item) => item,
+        TodoLine::Comment(_) => unreachable!(),
     }
 }
 
-fn parse_todo_list(todo_list_lines: &[&str]) -> Vec<TodoItem> {
-    todo_list_lines
+/// Queries the files of the commits not in the cache yet. Unresolvable commits are cached as
+/// touching no files, so they are not queried again.
+fn ensure_commit_files(
+    git: &Git,
+    commit_files: &mut HashMap<String, Vec<String>>,
+    hashes: &[&str],
+) {
+    let missing: Vec<_> = hashes
         .iter()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .map(|line| {
-            let (command, parameters) = line.split_once(' ').unwrap_or((line, ""));
-            TodoItem {
-                command: command.to_string(),
-                parameters: parameters.to_string(),
-            }
-        })
+        .copied()
+        .filter(|hash| !commit_files.contains_key(*hash))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    let mut files = git.commits_files(&missing);
+    for hash in missing {
+        let paths = files.remove(hash).unwrap_or_default();
+        commit_files.insert(hash.to_string(), paths);
+    }
+}
+
+/// The hashes of the commits that will be applied, in order.
+fn applied_commits(todo_lines: &[TodoLine]) -> Vec<&str> {
+    todo_lines
+        .iter()
+        .filter_map(TodoLine::item)
+        .filter(|item| !matches!(item.command, TodoCommand::Drop(_)))
+        .filter_map(|item| item.command.commit())
+        .map(|commit| commit.hash.as_str())
+        .filter(|hash| !hash.is_empty())
         .collect()
 }
 
-fn format_todo_list(todo_items: &[TodoItem]) -> Result<String> {
-    let mut str = String::new();
-    for item in todo_items {
-        writeln!(&mut str, "{} {}", item.command, item.parameters)?;
+/// An app on the todo list with the default configuration, for the tests. Git runs in a file,
+/// which fails like outside of any repository wherever the tests run.
+#[cfg(test)]
+pub fn test_app(todo_list: &str) -> App<'_> {
+    let git = Git::new(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+    App::new(todo_list, Config::default(), git)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::widgets::text_area::TextArea;
+
+    use super::{test_app, App, Mode};
+
+    fn todo_list(app: &App) -> String {
+        app.get_todo_list_string().unwrap()
+    }
+
+    #[test]
+    fn comments_keep_their_places() {
+        let mut app = test_app("pick 1a1a1a A\n\npick 2b2b2b B\n# help\n");
+        app.move_todo_item_down();
+        assert_eq!(todo_list(&app), "pick 2b2b2b B\n\npick 1a1a1a A\n# help\n");
+        assert_eq!(app.todo_list.selected(), 2);
+
+        // Not below the help
+        app.move_todo_item_down();
+        assert_eq!(todo_list(&app), "pick 2b2b2b B\n\npick 1a1a1a A\n# help\n");
+
+        // Comments do not move
+        app.todo_list.select(3);
+        app.move_todo_item_up();
+        assert_eq!(todo_list(&app), "pick 2b2b2b B\n\npick 1a1a1a A\n# help\n");
+    }
+
+    const ABCDE: &str =
+        "pick 1a1a1a A\npick 2b2b2b B\npick 3c3c3c C\npick 4d4d4d D\npick 5e5e5e E\n";
+
+    fn subjects(app: &App) -> String {
+        app.todo_list
+            .items()
+            .iter()
+            .filter_map(|line| Some(line.item()?.command.commit()?.subject.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn moving_a_range() {
+        let mut app = test_app(ABCDE);
+        app.todo_list.select(1);
+        app.todo_list.start_range();
+        app.todo_list.select(2);
+
+        app.move_todo_item_down();
+        assert_eq!(subjects(&app), "ADBCE");
+        assert_eq!(app.todo_list.multi_selection(), vec![2, 3]);
+        app.move_todo_item_up();
+        app.move_todo_item_up();
+        assert_eq!(subjects(&app), "BCADE");
+        assert_eq!(app.todo_list.multi_selection(), vec![0, 1]);
+
+        // At the top already
+        app.move_todo_item_up();
+        assert_eq!(subjects(&app), "BCADE");
+        app.undo();
+        assert_eq!(subjects(&app), "ABCDE");
+    }
+
+    #[test]
+    fn moving_marked_lines() {
+        let mut app = test_app(ABCDE);
+        app.todo_list.select(1);
+        app.todo_list.toggle_mark();
+        app.todo_list.select(3);
+        app.todo_list.toggle_mark();
+
+        app.move_todo_item_down();
+        assert_eq!(subjects(&app), "ACBED");
+        assert_eq!(app.todo_list.multi_selection(), vec![2, 4]);
+        assert_eq!(app.todo_list.selected(), 4);
+
+        // At the bottom already
+        app.move_todo_item_down();
+        assert_eq!(subjects(&app), "ACBED");
+
+        app.move_todo_item_up();
+        app.move_todo_item_up();
+        assert_eq!(subjects(&app), "BADCE");
+        assert_eq!(app.todo_list.multi_selection(), vec![0, 2]);
+    }
+
+    #[test]
+    fn removing() {
+        let mut app = test_app("pick 1a1a1a A\npick 2b2b2b B\npick 3c3c3c C\n# help\n");
+        app.todo_list.select(0);
+        app.todo_list.toggle_mark();
+        app.todo_list.select(2);
+        app.todo_list.toggle_mark();
+        app.remove_todo_item();
+        assert_eq!(todo_list(&app), "pick 2b2b2b B\n# help\n");
+        assert!(!app.todo_list.has_multi_selection());
+        assert_eq!(app.todo_list.selected(), 0);
+
+        // Comments are not removed
+        app.todo_list.select(1);
+        app.remove_todo_item();
+        assert_eq!(todo_list(&app), "pick 2b2b2b B\n# help\n");
+
+        app.undo();
+        assert_eq!(
+            todo_list(&app),
+            "pick 1a1a1a A\npick 2b2b2b B\npick 3c3c3c C\n# help\n"
+        );
+    }
+
+    #[test]
+    fn duplicating() {
+        let mut app = test_app(ABCDE);
+        app.todo_list.select(3);
+        app.todo_list.start_range();
+        app.todo_list.select(1);
+        app.duplicate_todo_item();
+        assert_eq!(subjects(&app), "ABCDBCDE");
+        assert_eq!(app.todo_list.multi_selection(), vec![4, 5, 6]);
+        assert_eq!(app.todo_list.selected(), 6);
+
+        app.todo_list.clear_multi_selection();
+        app.todo_list.select(0);
+        app.duplicate_todo_item();
+        assert_eq!(subjects(&app), "AABCDBCDE");
+        assert!(!app.todo_list.has_multi_selection());
+        assert_eq!(app.todo_list.selected(), 1);
+    }
+
+    #[test]
+    fn dropping_on_remove() {
+        let mut app = test_app("pick 1a1a1a A\nexec make\ndrop 2b2b2b B\n");
+        app.drop_on_remove = true;
+
+        // Lines without a commit are removed
+        app.todo_list.select(1);
+        app.remove_todo_item();
+        assert_eq!(todo_list(&app), "pick 1a1a1a A\ndrop 2b2b2b B\n");
+
+        // Some commits are not dropped yet
+        app.todo_list.select(0);
+        app.todo_list.start_range();
+        app.todo_list.select(1);
+        app.remove_todo_item();
+        assert_eq!(todo_list(&app), "drop 1a1a1a A\ndrop 2b2b2b B\n");
+        app.remove_todo_item();
+        assert_eq!(todo_list(&app), "pick 1a1a1a A\npick 2b2b2b B\n");
+
+        app.undo();
+        app.undo();
+        app.undo();
+        assert_eq!(todo_list(&app), "pick 1a1a1a A\nexec make\ndrop 2b2b2b B\n");
+        // Nothing else was recorded
+        app.undo();
+        assert_eq!(todo_list(&app), "pick 1a1a1a A\nexec make\ndrop 2b2b2b B\n");
+    }
+
+    #[test]
+    fn inserting_exec() {
+        let mut app = test_app("pick 1a1a1a A\nfixup 2b2b2b a\npick 3c3c3c B\nexec make\n# help\n");
+        app.todo_list.select(2);
+
+        // After the fixups, and not again after B
+        app.insert_exec_after(&[0, 1, 2, 3, 4], "make");
+        assert_eq!(
+            todo_list(&app),
+            "pick 1a1a1a A\nfixup 2b2b2b a\nexec make\npick 3c3c3c B\nexec make\n# help\n"
+        );
+        assert_eq!(app.todo_list.selected(), 3);
+
+        // Only after the selected commits
+        app.insert_exec_after(&[3], "cargo test");
+        assert_eq!(
+            todo_list(&app),
+            "pick 1a1a1a A\nfixup 2b2b2b a\nexec make\npick 3c3c3c B\nexec cargo test\nexec make\n# help\n"
+        );
+        assert_eq!(app.todo_list.selected(), 3);
+
+        // Nothing to insert
+        app.insert_exec_after(&[3], "cargo test");
+        app.undo();
+        assert_eq!(
+            todo_list(&app),
+            "pick 1a1a1a A\nfixup 2b2b2b a\nexec make\npick 3c3c3c B\nexec make\n# help\n"
+        );
+    }
+
+    #[test]
+    fn moving_groups() {
+        let mut app =
+            test_app("pick 1a1a1a A\nfixup 2b2b2b a\npick 3c3c3c B\nsquash 4d4d4d b\n# help\n");
+
+        // The whole group goes down past the other one
+        app.todo_list.select(0);
+        app.move_todo_item_down();
+        assert_eq!(
+            todo_list(&app),
+            "pick 3c3c3c B\nsquash 4d4d4d b\npick 1a1a1a A\nfixup 2b2b2b a\n# help\n"
+        );
+        assert_eq!(app.todo_list.selected(), 2);
+
+        // And back up
+        app.move_todo_item_up();
+        assert_eq!(
+            todo_list(&app),
+            "pick 1a1a1a A\nfixup 2b2b2b a\npick 3c3c3c B\nsquash 4d4d4d b\n# help\n"
+        );
+        assert_eq!(app.todo_list.selected(), 0);
+    }
+
+    #[test]
+    fn moving_groups_with_other_lines() {
+        // The exec goes with the group, the comment keeps its place
+        let mut app = test_app("pick 1a1a1a A\nexec make\n# note\nfixup 2b2b2b a\npick 3c3c3c B\n");
+        app.todo_list.select(0);
+        app.move_todo_item_down();
+        assert_eq!(
+            todo_list(&app),
+            "pick 3c3c3c B\npick 1a1a1a A\n# note\nexec make\nfixup 2b2b2b a\n"
+        );
+        assert_eq!(app.todo_list.selected(), 1);
+    }
+
+    #[test]
+    fn moving_past_a_group() {
+        let mut app = test_app("pick 1a1a1a A\nfixup 2b2b2b a\npick 3c3c3c B\n");
+        app.todo_list.select(2);
+        app.move_todo_item_up();
+        assert_eq!(
+            todo_list(&app),
+            "pick 3c3c3c B\npick 1a1a1a A\nfixup 2b2b2b a\n"
+        );
+        assert_eq!(app.todo_list.selected(), 0);
+    }
+
+    #[test]
+    fn quitting_past_warnings() {
+        let mut app = test_app("pick 1a1a1a A\nreset main\npick 2b2b2b B\n");
+        app.ask_rebase_confirmation();
+        assert!(matches!(
+            app.mode,
+            Mode::ShowingProblems {
+                can_proceed: true,
+                ..
+            }
+        ));
+        assert_eq!(app.todo_list.selected(), 1);
+
+        let mut app = test_app("reset topic\nlabel topic\nreset main\n");
+        app.ask_rebase_confirmation();
+        assert!(matches!(
+            app.mode,
+            Mode::ShowingProblems {
+                can_proceed: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn acting_on_shown_lines_only() {
+        let mut app = test_app(ABCDE);
+        app.todo_list.set_shown(Some(vec![0, 4]));
+        app.todo_list.select(0);
+        app.todo_list.start_range();
+        app.todo_list.select(4);
+        assert_eq!(app.todo_list.multi_selection(), vec![0, 4]);
+
+        app.remove_todo_item();
+        app.todo_list.set_shown(None);
+        assert_eq!(subjects(&app), "BCD");
+    }
+
+    fn edit_message(app: &mut App, index: usize, message: &str) {
+        app.todo_list.select(index);
+        app.start_message_edition();
+        let Mode::EditingMessage { input, .. } = &mut app.mode else {
+            panic!("not editing the message");
+        };
+        *input = TextArea::new(message);
+        app.confirm_message_edition();
+    }
+
+    #[test]
+    fn messages_written_ahead() {
+        let amend = "exec printf '%s\\n' 'New' | git commit --amend -F -\n";
+        let mut app = test_app("reword 1a1a1a A\nfixup 2b2b2b a\npick 3c3c3c B\n");
+
+        // After the fixups, in the group of the commit
+        edit_message(&mut app, 0, "New");
+        assert_eq!(
+            todo_list(&app),
+            format!("pick 1a1a1a A\nfixup 2b2b2b a\n{amend}pick 3c3c3c B\n")
+        );
+
+        // Moving over the group keeps the message with its commit
+        app.todo_list.select(3);
+        app.move_todo_item_up();
+        assert_eq!(
+            todo_list(&app),
+            format!("pick 3c3c3c B\npick 1a1a1a A\nfixup 2b2b2b a\n{amend}")
+        );
+        app.move_todo_item_down();
+        app.move_todo_item_up();
+        assert_eq!(
+            todo_list(&app),
+            format!("pick 3c3c3c B\npick 1a1a1a A\nfixup 2b2b2b a\n{amend}")
+        );
+
+        // Found again from the group, and removed with an empty message
+        app.todo_list.select(2);
+        app.start_message_edition();
+        assert!(
+            matches!(&app.mode, Mode::EditingMessage { input, .. } if input.content() == "New")
+        );
+        app.mode = Mode::Main;
+        edit_message(&mut app, 1, "");
+        assert_eq!(
+            todo_list(&app),
+            "pick 3c3c3c B\nreword 1a1a1a A\nfixup 2b2b2b a\n"
+        );
     }
-    Ok(str)
 }
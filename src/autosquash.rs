@@ -0,0 +1,154 @@
+use crate::todo::{MessageOption, TodoCommand, TodoLine};
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Fixup,
+    Squash,
+    Amend,
+}
+
+/// Strips the `fixup! `, `squash! ` or `amend! ` prefixes, as git does: the kind is given by
+/// the first one, and several of them may be stacked.
+fn parse_subject(subject: &str) -> Option<(Kind, &str)> {
+    let (kind, mut target) = strip(subject)?;
+    while let Some((_, rest)) = strip(target) {
+        target = rest;
+    }
+    Some((kind, target.trim_start()))
+}
+
+fn strip(subject: &str) -> Option<(Kind, &str)> {
+    [
+        ("fixup! ", Kind::Fixup),
+        ("squash! ", Kind::Squash),
+        ("amend! ", Kind::Amend),
+    ]
+    .into_iter()
+    .find_map(|(prefix, kind)| Some((kind, subject.strip_prefix(prefix)?)))
+}
+
+/// Rearranges the lines like `git rebase --autosquash`: each `fixup!`, `squash!` or `amend!`
+/// commit is moved after the earlier commit it refers to, by subject, hash or subject prefix,
+/// and gets the matching command. Returns `None` if nothing changes.
+pub fn autosquash(lines: &[TodoLine]) -> Option<Vec<TodoLine>> {
+    // The commits seen so far, as (index, hash, subject)
+    let mut seen: Vec<(usize, &str, &str)> = Vec::new();
+    // The index of the commit each line goes after, if it is moved
+    let mut targets: Vec<Option<(usize, Kind)>> = vec![None; lines.len()];
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(item) = line.item() else {
+            continue;
+        };
+        let Some(commit) = item.command.commit() else {
+            continue;
+        };
+        if matches!(item.command, TodoCommand::Drop(_)) {
+            continue;
+        }
+
+        if let Some((kind, target)) = parse_subject(&commit.subject) {
+            let found = seen
+                .iter()
+                .find(|(_, _, subject)| *subject == target)
+                .or_else(|| {
+                    let is_hash = !target.is_empty() && !target.contains(' ');
+                    seen.iter()
+                        .find(|(_, hash, _)| is_hash && hash.starts_with(target))
+                })
+                .or_else(|| {
+                    seen.iter()
+                        .find(|(_, _, subject)| !target.is_empty() && subject.starts_with(target))
+                });
+            if let Some((target_index, _, _)) = found {
+                // A fixup of a fixup goes with the commit they both fix
+                let root = targets[*target_index].map_or(*target_index, |(root, _)| root);
+                targets[index] = Some((root, kind));
+            }
+        }
+        seen.push((index, &commit.hash, &commit.subject));
+    }
+
+    let mut rearranged = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if targets[index].is_some() {
+            continue;
+        }
+        rearranged.push(line.clone());
+
+        let followers = targets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, target)| target.filter(|(root, _)| *root == index).map(|t| (i, t.1)));
+        for (follower, kind) in followers {
+            let mut line = lines[follower].clone();
+            if let TodoLine::Item(item) = &mut line {
+                let (name, message_option) = match kind {
+                    Kind::Fixup => ("fixup", None),
+                    Kind::Squash => ("squash", None),
+                    Kind::Amend => ("fixup", Some(MessageOption::Use)),
+                };
+                let command = TodoCommand::parse(name, &item.command.parameters());
+                if command.name() != item.command.name()
+                    || item.command.message_option() != message_option
+                {
+                    item.command = command;
+                    item.command.set_message_option(message_option);
+                }
+            }
+            rearranged.push(line);
+        }
+    }
+
+    (rearranged != lines).then_some(rearranged)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::{format_todo_list, parse_todo_list};
+
+    use super::autosquash;
+
+    fn arranged(lines: &[&str]) -> Option<String> {
+        let lines = autosquash(&parse_todo_list(lines, '#'))?;
+        Some(format_todo_list(&lines, false).unwrap())
+    }
+
+    #[test]
+    fn arrangement() {
+        let lines = [
+            "pick 1a1a1a Add parser",
+            "pick 2b2b2b Add lexer",
+            "exec make",
+            "pick 3c3c3c fixup! Add parser",
+            "pick 4d4d4d squash! 2b2b2b",
+            "pick 5e5e5e amend! Add pars",
+            "pick 6f6f6f fixup! fixup! Add parser",
+            "pick 7a7a7a fixup! Unknown",
+        ];
+
+        assert_eq!(
+            arranged(&lines).unwrap(),
+            "pick 1a1a1a Add parser\n\
+             fixup 3c3c3c fixup! Add parser\n\
+             fixup -C 5e5e5e amend! Add pars\n\
+             fixup 6f6f6f fixup! fixup! Add parser\n\
+             pick 2b2b2b Add lexer\n\
+             squash 4d4d4d squash! 2b2b2b\n\
+             exec make\n\
+             pick 7a7a7a fixup! Unknown\n"
+        );
+    }
+
+    #[test]
+    fn nothing_to_arrange() {
+        let lines = [
+            "pick 1a1a1a Add parser",
+            "fixup 3c3c3c fixup! Add parser",
+            "pick 7a7a7a fixup! Later",
+            "pick 8b8b8b Later",
+        ];
+
+        assert_eq!(arranged(&lines), None);
+    }
+}
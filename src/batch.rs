@@ -0,0 +1,253 @@
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    app::App,
+    todo::{TodoCommand, TodoLine},
+    validation::validate,
+};
+
+/// A line of the list, by its number or by the hash of its commit.
+#[derive(PartialEq, Eq, Debug)]
+enum Target {
+    /// Also an abbreviated hash made of digits only, if there is no such line.
+    Line(usize),
+    Commit(String),
+}
+
+impl Target {
+    fn parse(str: &str) -> Self {
+        match str.parse::<usize>() {
+            // Line numbers are not written with leading zeros, but hashes may start with them
+            Ok(number) if !str.starts_with('0') || str == "0" => Target::Line(number),
+            _ => Target::Commit(str.to_string()),
+        }
+    }
+
+    /// The index of the line in the list.
+    fn resolve(&self, todo_lines: &[TodoLine]) -> Result<usize> {
+        match self {
+            Target::Line(number) if *number >= 1 && *number <= todo_lines.len() => Ok(number - 1),
+            Target::Line(number) => find_commit(todo_lines, &number.to_string())
+                .ok_or_else(|| anyhow!("there is no line or commit {number}")),
+            Target::Commit(hash) => {
+                find_commit(todo_lines, hash).ok_or_else(|| anyhow!("there is no commit {hash}"))
+            }
+        }
+    }
+}
+
+fn find_commit(todo_lines: &[TodoLine], hash: &str) -> Option<usize> {
+    todo_lines.iter().position(|line| {
+        let commit = line.item().and_then(|item| item.command.commit());
+        // The hash may be longer than the abbreviated one of the list
+        commit.is_some_and(|commit| {
+            !commit.hash.is_empty()
+                && (commit.hash.starts_with(hash) || hash.starts_with(&commit.hash))
+        })
+    })
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum Operation {
+    Move {
+        from: Target,
+        to: Target,
+    },
+    /// Also used for `drop`.
+    Set {
+        target: Target,
+        command: String,
+    },
+    ExecAfterEach(String),
+    Autosquash,
+}
+
+/// The commands `set` accepts: those of a line with a commit.
+const SET_COMMANDS: [&str; 6] = ["pick", "reword", "edit", "squash", "fixup", "drop"];
+
+impl Operation {
+    fn parse(line: &str) -> Result<Self> {
+        let (name, arguments) = line.split_once(' ').unwrap_or((line, ""));
+        let arguments = arguments.trim();
+        let words: Vec<_> = arguments.split_whitespace().collect();
+
+        Ok(match (name, words.as_slice()) {
+            ("move", [from, "to", to]) => Operation::Move {
+                from: Target::parse(from),
+                to: Target::parse(to),
+            },
+            ("set", [target, command]) => {
+                let command = TodoCommand::parse(command, "");
+                if !SET_COMMANDS.contains(&command.name()) {
+                    bail!("cannot set the command to {}", command.name());
+                }
+                Operation::Set {
+                    target: Target::parse(target),
+                    command: command.name().to_string(),
+                }
+            }
+            ("drop", [target]) => Operation::Set {
+                target: Target::parse(target),
+                command: "drop".to_string(),
+            },
+            ("exec-after-each", [_, ..]) => {
+                let command = arguments
+                    .strip_prefix('"')
+                    .and_then(|arguments| arguments.strip_suffix('"'))
+                    .unwrap_or(arguments);
+                Operation::ExecAfterEach(command.to_string())
+            }
+            ("autosquash", []) => Operation::Autosquash,
+            ("move" | "set" | "drop" | "exec-after-each" | "autosquash", _) => {
+                bail!("wrong arguments for {name}")
+            }
+            _ => bail!("unknown operation {name}"),
+        })
+    }
+
+    fn apply(&self, app: &mut App) -> Result<()> {
+        match self {
+            Operation::Move { from, to } => {
+                let from = from.resolve(app.todo_list.items())?;
+                let to = to.resolve(app.todo_list.items())?;
+                select(app, from);
+                app.start_drag();
+                app.drag_to(to);
+                app.end_drag();
+            }
+            Operation::Set { target, command } => {
+                let index = target.resolve(app.todo_list.items())?;
+                if app.todo_list.items()[index]
+                    .item()
+                    .and_then(|item| item.command.commit())
+                    .is_none()
+                {
+                    bail!("line {} has no commit", index + 1);
+                }
+                select(app, index);
+                app.set_command(command);
+            }
+            Operation::ExecAfterEach(command) => {
+                let indices: Vec<_> = (0..app.todo_list.items().len()).collect();
+                app.insert_exec_after(&indices, command);
+            }
+            Operation::Autosquash => {
+                app.preview_autosquash();
+                app.apply_autosquash();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn select(app: &mut App, index: usize) {
+    app.todo_list.clear_multi_selection();
+    app.todo_list.select(index);
+}
+
+/// Runs the operations of the script, one per line, on the todo list of the app. Blank lines
+/// and lines starting with `#` are ignored. Lines are referred to by their number in the list
+/// as it is when the operation runs, or by the hash of their commit.
+pub fn apply_script(app: &mut App, script: &str) -> Result<()> {
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        Operation::parse(line)
+            .and_then(|operation| operation.apply(app))
+            .with_context(|| format!("Cannot apply line {} of the script: {line}", number + 1))?;
+    }
+
+    let (warnings, problems): (Vec<_>, Vec<_>) = validate(app.todo_list.items())
+        .into_iter()
+        .partition(|problem| problem.warning);
+    for warning in warnings {
+        eprintln!("Warning on line {}: {}", warning.index + 1, warning.message);
+    }
+    if !problems.is_empty() {
+        let problems: Vec<_> = problems
+            .iter()
+            .map(|problem| format!("line {}: {}", problem.index + 1, problem.message))
+            .collect();
+        bail!(
+            "The rebase would fail on these problems:\n{}",
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::test_app;
+
+    use super::{apply_script, Operation, Target};
+
+    #[test]
+    fn parse_operations() {
+        assert_eq!(
+            Operation::parse("move 5 to 1").unwrap(),
+            Operation::Move {
+                from: Target::Line(5),
+                to: Target::Line(1),
+            }
+        );
+        assert_eq!(
+            Operation::parse("set abc123 f").unwrap(),
+            Operation::Set {
+                target: Target::Commit("abc123".to_string()),
+                command: "fixup".to_string(),
+            }
+        );
+        assert_eq!(
+            Operation::parse("drop abc123").unwrap(),
+            Operation::Set {
+                target: Target::Commit("abc123".to_string()),
+                command: "drop".to_string(),
+            }
+        );
+        assert_eq!(
+            Operation::parse("exec-after-each \"cargo test\"").unwrap(),
+            Operation::ExecAfterEach("cargo test".to_string())
+        );
+        assert_eq!(
+            Operation::parse("exec-after-each make  check").unwrap(),
+            Operation::ExecAfterEach("make  check".to_string())
+        );
+
+        assert!(Operation::parse("move 5 1").is_err());
+        assert!(Operation::parse("set 3 exec").is_err());
+        assert!(Operation::parse("exec-after-each").is_err());
+        assert!(Operation::parse("swap 1 2").is_err());
+    }
+
+    #[test]
+    fn apply() {
+        let todo_list = "pick 1a1a1a A\npick 2b2b2b B\npick 1234567 C\npick 0123456 D\n# help\n";
+        let mut app = test_app(todo_list);
+        let script = "\
+            # Hashes made of digits only\n\
+            move 1234567 to 1\n\
+            set 0123456 squash\n\
+            drop 1a1a1a\n\
+            \n\
+            exec-after-each make\n";
+        apply_script(&mut app, script).unwrap();
+        assert_eq!(
+            app.get_todo_list_string().unwrap(),
+            "pick 1234567 C\n\
+             exec make\n\
+             drop 1a1a1a A\n\
+             pick 2b2b2b B\n\
+             squash 0123456 D\n\
+             exec make\n\
+             # help\n"
+        );
+
+        let mut app = test_app(todo_list);
+        assert!(apply_script(&mut app, "move 9 to 1").is_err());
+        // The resulting list is invalid
+        assert!(apply_script(&mut app, "set 1 fixup").is_err());
+    }
+}
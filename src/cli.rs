@@ -0,0 +1,106 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+
+pub const USAGE: &str = "\
+Usage:
+  git-interactive-rebase-editor <TODO_FILE>
+      Edits the todo list of an interactive rebase, as the git sequence editor.
+  git-interactive-rebase-editor --apply <SCRIPT_FILE> <TODO_FILE>
+      Applies the operations of a script to the todo list, without a terminal.
+  git-interactive-rebase-editor split
+      Splits the commit the rebase stopped at, following its split plan.
+
+Options:
+  -h, --help     Prints this help
+  -V, --version  Prints the version";
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Command {
+    Help,
+    Version,
+    Split,
+    Apply { script_path: PathBuf, path: PathBuf },
+    Edit { path: PathBuf },
+}
+
+/// The paths are taken as given, even if they are not valid UTF-8.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command> {
+    let args: Vec<OsString> = args.into_iter().collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+    if args.iter().any(|arg| arg == "-V" || arg == "--version") {
+        return Ok(Command::Version);
+    }
+
+    let args: Vec<&OsStr> = args.iter().map(OsString::as_os_str).collect();
+    let command = match args[..] {
+        [] => bail!("Missing the todo file\n\n{USAGE}"),
+        [arg] if arg == "split" => Command::Split,
+        [option, script_path, path] if option == "--apply" => Command::Apply {
+            script_path: script_path.into(),
+            path: path.into(),
+        },
+        [option, ..] if option == "--apply" => {
+            bail!("--apply takes a script file and the todo file\n\n{USAGE}")
+        }
+        [option, ..] if option.to_string_lossy().starts_with('-') => {
+            bail!("Unknown option {}\n\n{USAGE}", option.to_string_lossy())
+        }
+        [path] => Command::Edit { path: path.into() },
+        _ => bail!("Too many arguments\n\n{USAGE}"),
+    };
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, path::PathBuf};
+
+    use super::{parse, Command};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            parse(args(&["git-rebase-todo"])).unwrap(),
+            Command::Edit {
+                path: PathBuf::from("git-rebase-todo")
+            }
+        );
+        assert_eq!(
+            parse(args(&["--apply", "ops", "git-rebase-todo"])).unwrap(),
+            Command::Apply {
+                script_path: PathBuf::from("ops"),
+                path: PathBuf::from("git-rebase-todo")
+            }
+        );
+        assert_eq!(parse(args(&["split"])).unwrap(), Command::Split);
+        assert_eq!(parse(args(&["todo", "--help"])).unwrap(), Command::Help);
+        assert_eq!(parse(args(&["-V"])).unwrap(), Command::Version);
+
+        assert!(parse(args(&[])).is_err());
+        assert!(parse(args(&["--apply", "ops"])).is_err());
+        assert!(parse(args(&["--unknown"])).is_err());
+        assert!(parse(args(&["todo", "other"])).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = OsString::from_vec(b"todo-\xe9".to_vec());
+        assert_eq!(
+            parse([path.clone()]).unwrap(),
+            Command::Edit { path: path.into() }
+        );
+    }
+}
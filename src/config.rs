@@ -0,0 +1,79 @@
+use anyhow::Result;
+
+use crate::{git::Git, keys::Keys};
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum MissingCommitsCheck {
+    Ignore,
+    /// Unlike git, warning is the default, since removing a line here takes a single key press.
+    #[default]
+    Warn,
+    Error,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum Keymap {
+    #[default]
+    Default,
+    Vim,
+}
+
+#[derive(Default)]
+pub struct Config {
+    pub abbreviate_commands: bool,
+    /// `None` if not configured or set to `auto`.
+    pub comment_char: Option<char>,
+    pub missing_commits_check: MissingCommitsCheck,
+    /// Whether removing a commit turns it into a `drop` rather than deleting its line.
+    pub drop_on_remove: bool,
+    pub keymap: Keymap,
+    pub keys: Keys,
+    /// The command proposed when inserting `exec` lines, which is the last one used.
+    pub exec_command: Option<String>,
+}
+
+impl Config {
+    pub fn load(git: &Git) -> Result<Self> {
+        Ok(Config {
+            abbreviate_commands: git
+                .config_bool("rebase.abbreviateCommands")
+                .unwrap_or(false),
+            comment_char: git
+                .config("core.commentChar")
+                .and_then(|value| single_char(&value)),
+            missing_commits_check: match git
+                .config("rebase.missingCommitsCheck")
+                .map(|value| value.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("ignore") => MissingCommitsCheck::Ignore,
+                Some("error") => MissingCommitsCheck::Error,
+                _ => MissingCommitsCheck::Warn,
+            },
+            drop_on_remove: git
+                .config_bool("interactive-rebase-editor.dropOnRemove")
+                .unwrap_or(false),
+            keymap: match git
+                .config("interactive-rebase-editor.keymap")
+                .map(|value| value.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("vim") => Keymap::Vim,
+                _ => Keymap::Default,
+            },
+            keys: Keys::load(git)?,
+            exec_command: git.config(EXEC_COMMAND_KEY),
+        })
+    }
+}
+
+/// Set in the repository when inserting `exec` lines, to propose the same command next time.
+pub const EXEC_COMMAND_KEY: &str = "interactive-rebase-editor.execCommand";
+
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Some(char),
+        _ => None,
+    }
+}
@@ -0,0 +1,115 @@
+pub struct Diff {
+    pub commit: String,
+    pub word_diff: bool,
+    pub lines: Vec<String>,
+    files: Vec<usize>,
+    hunks: Vec<usize>,
+}
+
+impl Diff {
+    /// `text` is the stat summary followed by the patch, as output by `git show --stat --patch`.
+    pub fn new(commit: String, word_diff: bool, text: &str) -> Self {
+        let lines: Vec<_> = text.lines().map(str::to_string).collect();
+        let positions = |prefix: &str| {
+            lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.starts_with(prefix))
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        Diff {
+            commit,
+            word_diff,
+            files: positions("diff --git "),
+            hunks: positions("@@ "),
+            lines,
+        }
+    }
+
+    pub fn next_file(&self, line: usize) -> Option<usize> {
+        next_position(&self.files, line)
+    }
+
+    pub fn previous_file(&self, line: usize) -> Option<usize> {
+        previous_position(&self.files, line)
+    }
+
+    pub fn next_hunk(&self, line: usize) -> Option<usize> {
+        next_position(&self.hunks, line)
+    }
+
+    pub fn previous_hunk(&self, line: usize) -> Option<usize> {
+        previous_position(&self.hunks, line)
+    }
+
+    /// The index of the file that contains `line`, if it is not in the stat summary.
+    pub fn file_index(&self, line: usize) -> Option<usize> {
+        self.files.iter().rposition(|&position| position <= line)
+    }
+
+    pub fn file_position(&self, file_index: usize) -> Option<usize> {
+        self.files.get(file_index).copied()
+    }
+}
+
+fn next_position(positions: &[usize], line: usize) -> Option<usize> {
+    positions.iter().copied().find(|&position| position > line)
+}
+
+fn previous_position(positions: &[usize], line: usize) -> Option<usize> {
+    positions
+        .iter()
+        .copied()
+        .rev()
+        .find(|&position| position < line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diff;
+
+    const TEXT: &str = "\
+ a.txt | 2 +-
+ b.txt | 1 +
+ 2 files changed, 2 insertions(+), 1 deletion(-)
+
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-old
++new
+@@ -10 +10 @@
+ context
+diff --git a/b.txt b/b.txt
+index 3333333..4444444 100644
+--- a/b.txt
++++ b/b.txt
+@@ -1,0 +2 @@
++added
+";
+
+    #[test]
+    fn navigation() {
+        let diff = Diff::new("1a2b3c".to_string(), false, TEXT);
+
+        assert_eq!(diff.next_file(0), Some(4));
+        assert_eq!(diff.next_file(4), Some(13));
+        assert_eq!(diff.next_file(13), None);
+        assert_eq!(diff.previous_file(13), Some(4));
+        assert_eq!(diff.previous_file(4), None);
+
+        assert_eq!(diff.next_hunk(0), Some(8));
+        assert_eq!(diff.next_hunk(8), Some(11));
+        assert_eq!(diff.next_hunk(11), Some(17));
+        assert_eq!(diff.previous_hunk(17), Some(11));
+
+        assert_eq!(diff.file_index(2), None);
+        assert_eq!(diff.file_index(12), Some(0));
+        assert_eq!(diff.file_index(17), Some(1));
+        assert_eq!(diff.file_position(1), Some(13));
+    }
+}
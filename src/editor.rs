@@ -0,0 +1,73 @@
+use std::{
+    collections::hash_map::RandomState,
+    env, fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{bail, Context, Result};
+
+/// Runs the editor on the file, letting the shell split the editor command like git does.
+pub fn run(editor: &str, path: &Path) -> Result<()> {
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(editor)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Cannot run the editor {editor}"))?;
+    if !status.success() {
+        bail!("The editor {editor} failed: {status}");
+    }
+    Ok(())
+}
+
+/// Lets the text be edited in a temporary file, named `name` so that editors recognize it.
+pub fn edit_text(editor: &str, name: &str, text: &str) -> Result<String> {
+    let dir = create_temp_dir()?;
+    let path = dir.join(name);
+    let result = fs::write(&path, text)
+        .map_err(Into::into)
+        .and_then(|_| run(editor, &path))
+        .and_then(|_| Ok(String::from_utf8_lossy(&fs::read(&path)?).into_owned()));
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Creates a new directory, readable only by the user, under a random name. An existing one is
+/// never reused, as another user could have created it in the shared temporary directory.
+fn create_temp_dir() -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    let mut attempts = 0;
+    loop {
+        let random = RandomState::new().build_hasher().finish();
+        let dir = env::temp_dir().join(format!("gire-edit-{}-{random:016x}", process::id()));
+        match builder.create(&dir) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempts < 10 => {
+                attempts += 1;
+            }
+            result => return result.map(|_| dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::create_temp_dir;
+
+    #[test]
+    fn temp_dirs_are_new() {
+        let dir = create_temp_dir().unwrap();
+        let other = create_temp_dir().unwrap();
+        assert_ne!(dir, other);
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        fs::remove_dir(dir).unwrap();
+        fs::remove_dir(other).unwrap();
+    }
+}
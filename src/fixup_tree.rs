@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use crate::{
+    message::parse_amend_command,
+    todo::{TodoCommand, TodoLine},
+};
+
+/// The groups of lines ending up as a single commit: a commit followed by the `squash` and
+/// `fixup` lines folded into it, with the comments and `exec` lines between them, and the
+/// `exec` line setting its message if it was written ahead of time. Commits without such
+/// lines are not in any group.
+pub fn fixup_groups(lines: &[TodoLine]) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut parent = None;
+    for (index, line) in lines.iter().enumerate() {
+        let command = line.item().map(|item| &item.command);
+        match command {
+            // Without a parent, the line is invalid, which the validation reports
+            Some(TodoCommand::Squash(_) | TodoCommand::Fixup(..)) => {
+                extend_group(&mut groups, parent, index)
+            }
+            Some(TodoCommand::Exec(command)) if parse_amend_command(command).is_some() => {
+                extend_group(&mut groups, parent, index)
+            }
+            Some(
+                TodoCommand::Pick(_)
+                | TodoCommand::Reword(_)
+                | TodoCommand::Edit(_)
+                | TodoCommand::Merge { .. },
+            ) => parent = Some(index),
+            // Like git, the lines after them are still folded into the same commit
+            None | Some(TodoCommand::Exec(_)) => {}
+            _ => parent = None,
+        }
+    }
+    groups
+}
+
+/// Adds the line to the group of `parent`, with the lines between them.
+fn extend_group(groups: &mut Vec<Range<usize>>, parent: Option<usize>, index: usize) {
+    if let Some(start) = parent {
+        match groups.last_mut() {
+            Some(group) if group.start == start => group.end = index + 1,
+            _ => groups.push(start..index + 1),
+        }
+    }
+}
+
+/// The group the line is the parent of, if any.
+pub fn group_of_parent(groups: &[Range<usize>], index: usize) -> Option<&Range<usize>> {
+    groups.iter().find(|group| group.start == index)
+}
+
+/// The group the line is in, as the parent or as one of the folded lines.
+pub fn group_of_line(groups: &[Range<usize>], index: usize) -> Option<&Range<usize>> {
+    groups.iter().find(|group| group.contains(&index))
+}
+
+/// The indices, in ascending order, with the folded lines of the parents among them.
+pub fn with_folded_lines(indices: &[usize], groups: &[Range<usize>]) -> Vec<usize> {
+    let mut expanded: Vec<usize> = indices
+        .iter()
+        .flat_map(|&index| {
+            group_of_parent(groups, index)
+                .cloned()
+                .unwrap_or(index..index + 1)
+        })
+        .collect();
+    expanded.sort_unstable();
+    expanded.dedup();
+    expanded
+}
+
+/// The order of the lines after moving the selected ones, with their folded lines, one position
+/// up (-1) or down (1), as the old index at each new index. A group next to the selection is
+/// moved over as a whole rather than broken. Returns `None` if the selection is at the end of
+/// the list already.
+pub fn shifted_order(
+    len: usize,
+    selection: &[usize],
+    groups: &[Range<usize>],
+    delta: isize,
+) -> Option<Vec<usize>> {
+    let (first, last) = (selection[0], selection[selection.len() - 1]);
+    if (delta < 0 && first == 0) || (delta > 0 && last + 1 >= len) {
+        return None;
+    }
+
+    let mut order: Vec<usize> = (0..len).collect();
+    if last - first + 1 == selection.len() {
+        if delta < 0 {
+            let step = groups
+                .iter()
+                .find(|group| group.end == first)
+                .map_or(1, |group| group.len());
+            order[first - step..=last].rotate_left(step);
+        } else {
+            let step = group_of_parent(groups, last + 1).map_or(1, |group| group.len());
+            order[first..=last + step].rotate_right(step);
+        }
+    } else if delta < 0 {
+        for &index in selection {
+            order.swap(index, index - 1);
+        }
+    } else {
+        for &index in selection.iter().rev() {
+            order.swap(index, index + 1);
+        }
+    }
+    Some(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::parse_todo_list;
+
+    use super::{fixup_groups, with_folded_lines};
+
+    #[test]
+    fn groups() {
+        let lines = parse_todo_list(
+            &[
+                "fixup 0a0a0a Orphan",
+                "pick 1a1a1a A",
+                "fixup 2b2b2b fixup! A",
+                "squash 3c3c3c squash! A",
+                "pick 4d4d4d B",
+                "exec make",
+                "fixup 5e5e5e After exec",
+                "reword 6f6f6f C",
+                "# comment",
+                "fixup -C 7a7a7a amend! C",
+                "# comment",
+            ],
+            '#',
+        );
+        let groups = fixup_groups(&lines);
+        assert_eq!(groups, vec![1..4, 4..7, 7..10]);
+
+        assert_eq!(with_folded_lines(&[1], &groups), vec![1, 2, 3]);
+        assert_eq!(with_folded_lines(&[2, 5], &groups), vec![2, 5]);
+        assert_eq!(
+            with_folded_lines(&[1, 2, 7], &groups),
+            vec![1, 2, 3, 7, 8, 9]
+        );
+    }
+}
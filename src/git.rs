@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Result};
+
+pub struct CommitDetails {
+    pub hash: String,
+    pub author: String,
+    pub author_date: String,
+    pub committer: String,
+    pub commit_date: String,
+    pub message: String,
+    pub stat: Vec<String>,
+}
+
+pub struct Git {
+    dir: PathBuf,
+}
+
+impl Git {
+    /// Runs git commands from `dir`, which may be any directory inside the repository,
+    /// including the `.git` directory where the todo file lives.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Git {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.dir);
+        command
+    }
+
+    fn output(&self, args: &[&str]) -> Option<String> {
+        let output = self.command().args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Runs a command that changes the repository, with its error output as the error.
+    pub fn run(&self, args: &[&str]) -> Result<String> {
+        let output = self.command().args(args).output()?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The path of a file in the git directory, such as `rebase-merge/done`.
+    pub fn git_path(&self, name: &str) -> Option<PathBuf> {
+        let path = self.output(&["rev-parse", "--git-path", name])?;
+        Some(self.dir.join(path.trim_end_matches('\n')))
+    }
+
+    pub fn config(&self, key: &str) -> Option<String> {
+        self.output(&["config", "--get", key])
+            .map(|value| value.trim_end_matches('\n').to_string())
+    }
+
+    pub fn config_bool(&self, key: &str) -> Option<bool> {
+        match self
+            .output(&["config", "--type=bool", "--get", key])?
+            .trim()
+        {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Sets the variable in the configuration of the repository. Returns `false` if it failed.
+    pub fn set_config(&self, key: &str, value: &str) -> bool {
+        self.output(&["config", key, value]).is_some()
+    }
+
+    /// The names and values of the variables matching `regexp`, in the order of the files.
+    pub fn config_regexp(&self, regexp: &str) -> Vec<(String, String)> {
+        self.output(&["config", "--get-regexp", regexp])
+            .unwrap_or_default()
+            .lines()
+            .map(|line| {
+                let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+                (name.to_string(), value.to_string())
+            })
+            .collect()
+    }
+
+    /// The editor git runs for commit messages, from `GIT_EDITOR`, `core.editor`, `VISUAL` or
+    /// `EDITOR`.
+    pub fn editor(&self) -> Option<String> {
+        self.output(&["var", "GIT_EDITOR"])
+            .map(|editor| editor.trim_end_matches('\n').to_string())
+    }
+
+    pub fn is_repository(&self) -> bool {
+        self.output(&["rev-parse", "--git-dir"]).is_some()
+    }
+
+    pub fn commit_details(&self, commit: &str) -> Option<CommitDetails> {
+        const FORMAT: &str = "--format=%H%x00%an <%ae>%x00%ad%x00%cn <%ce>%x00%cd%x00%B%x00";
+        let output = self.output(&[
+            "show",
+            "--no-color",
+            "--stat",
+            "--date=iso",
+            FORMAT,
+            commit,
+            "--",
+        ])?;
+
+        let mut fields = output.splitn(7, '\0').map(str::to_string);
+        let mut next_field = || fields.next();
+        Some(CommitDetails {
+            hash: next_field()?,
+            author: next_field()?,
+            author_date: next_field()?,
+            committer: next_field()?,
+            commit_date: next_field()?,
+            message: next_field()?.trim_end().to_string(),
+            stat: next_field()?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// The stat summary followed by the patch of `commit`.
+    pub fn commit_diff(&self, commit: &str, word_diff: bool) -> Option<String> {
+        let mut args = vec!["show", "--no-color", "--stat", "--patch", "--format="];
+        if word_diff {
+            args.push("--word-diff=plain");
+        }
+        args.extend([commit, "--"]);
+        self.output(&args)
+    }
+
+    /// The paths touched by each of `commits`, keyed by the commit names as given.
+    pub fn commits_files(&self, commits: &[&str]) -> HashMap<String, Vec<String>> {
+        self.commits_lines(commits, &["--name-only", "--format=%x00%H"])
+    }
+
+    /// The author name and email of each of `commits`, keyed by the commit names as given.
+    pub fn commits_authors(&self, commits: &[&str]) -> HashMap<String, String> {
+        self.commits_lines(commits, &["--no-patch", "--format=%x00%H%n%an <%ae>"])
+            .into_iter()
+            .filter_map(|(commit, lines)| Some((commit, lines.into_iter().next()?)))
+            .collect()
+    }
+
+    /// The non-empty lines output by `git show` for each of `commits`. The format in `args`
+    /// must start each commit with a NUL and the full hash in its own line.
+    fn commits_lines(&self, commits: &[&str], args: &[&str]) -> HashMap<String, Vec<String>> {
+        let mut all_args = vec!["show", "--no-color"];
+        all_args.extend(args);
+        all_args.extend(commits);
+        all_args.push("--");
+
+        match self.output(&all_args) {
+            Some(output) => {
+                let mut result = HashMap::new();
+                for entry in output.split('\0').skip(1) {
+                    let mut lines = entry.lines();
+                    let Some(full_hash) = lines.next() else {
+                        continue;
+                    };
+                    if let Some(commit) = commits.iter().find(|c| full_hash.starts_with(**c)) {
+                        let lines = lines.filter(|line| !line.is_empty());
+                        result.insert(commit.to_string(), lines.map(str::to_string).collect());
+                    }
+                }
+                result
+            }
+            // Some commit could not be resolved, so try them one by one
+            None if commits.len() > 1 => commits
+                .iter()
+                .flat_map(|commit| self.commits_lines(&[commit], args))
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> History<T> {
+    pub fn new() -> Self {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Records the state from before a change.
+    pub fn record(&mut self, state: T) {
+        self.undo.push(state);
+        self.redo.clear();
+    }
+
+    /// Returns the state to restore, if any. `current` becomes available for redoing.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        Some(state)
+    }
+
+    /// Returns the state to restore, if any. `current` becomes available for undoing.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::new();
+        assert_eq!(history.undo(0), None);
+
+        history.record(0);
+        history.record(1);
+
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), None);
+
+        assert_eq!(history.undo(2), Some(1));
+        history.record(1);
+        assert_eq!(history.redo(3), None);
+    }
+}
@@ -0,0 +1,512 @@
+use std::{collections::HashMap, env, fmt, fs, io, path::PathBuf, str::FromStr};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::git::Git;
+
+/// Where an action is available.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Context {
+    Main,
+    Diff,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    ClearSelection,
+    Quit,
+    Insert,
+    ShowOriginal,
+    Undo,
+    Redo,
+    MoveUp,
+    MoveDown,
+    ExtendUp,
+    ExtendDown,
+    Up,
+    Down,
+    First,
+    Last,
+    PageUp,
+    PageDown,
+    Mark,
+    SelectRange,
+    Edit,
+    Remove,
+    Duplicate,
+    ToggleDropOnRemove,
+    ToggleDetails,
+    ViewDiff,
+    Search,
+    Filter,
+    NextMatch,
+    PreviousMatch,
+    Autosquash,
+    InsertExec,
+    EditMessage,
+    Split,
+    EditAsText,
+    ToggleFold,
+
+    DiffClose,
+    DiffToggleWordDiff,
+    DiffNextFile,
+    DiffPreviousFile,
+    DiffNextHunk,
+    DiffPreviousHunk,
+    DiffUp,
+    DiffDown,
+    DiffPageUp,
+    DiffPageDown,
+    DiffTop,
+    DiffBottom,
+}
+
+struct ActionInfo {
+    action: Action,
+    /// The name used in the configuration.
+    name: &'static str,
+    context: Context,
+    default_keys: &'static [&'static str],
+}
+
+const fn info(
+    action: Action,
+    name: &'static str,
+    context: Context,
+    default_keys: &'static [&'static str],
+) -> ActionInfo {
+    ActionInfo {
+        action,
+        name,
+        context,
+        default_keys,
+    }
+}
+
+/// When a key is bound to several actions of a context, the first available one is run.
+const ACTIONS: &[ActionInfo] = &[
+    info(
+        Action::ClearSelection,
+        "clear-selection",
+        Context::Main,
+        &["esc"],
+    ),
+    info(Action::Quit, "quit", Context::Main, &["esc", "q"]),
+    info(Action::Insert, "insert", Context::Main, &["insert"]),
+    info(Action::ShowOriginal, "show-original", Context::Main, &["o"]),
+    info(Action::Undo, "undo", Context::Main, &["u", "ctrl+z"]),
+    info(Action::Redo, "redo", Context::Main, &["ctrl+r", "ctrl+y"]),
+    info(Action::MoveUp, "move-up", Context::Main, &["ctrl+up"]),
+    info(Action::MoveDown, "move-down", Context::Main, &["ctrl+down"]),
+    info(Action::ExtendUp, "extend-up", Context::Main, &["shift+up"]),
+    info(
+        Action::ExtendDown,
+        "extend-down",
+        Context::Main,
+        &["shift+down"],
+    ),
+    info(Action::Up, "up", Context::Main, &["up"]),
+    info(Action::Down, "down", Context::Main, &["down"]),
+    info(Action::First, "first", Context::Main, &["home"]),
+    info(Action::Last, "last", Context::Main, &["end"]),
+    info(Action::PageUp, "page-up", Context::Main, &["pageup"]),
+    info(Action::PageDown, "page-down", Context::Main, &["pagedown"]),
+    info(Action::Mark, "mark", Context::Main, &["space"]),
+    info(Action::SelectRange, "select-range", Context::Main, &["s"]),
+    info(Action::Edit, "edit", Context::Main, &["enter"]),
+    info(Action::Remove, "remove", Context::Main, &["delete"]),
+    info(Action::Duplicate, "duplicate", Context::Main, &["2"]),
+    info(
+        Action::ToggleDropOnRemove,
+        "toggle-drop-on-remove",
+        Context::Main,
+        &["x"],
+    ),
+    info(
+        Action::ToggleDetails,
+        "toggle-details",
+        Context::Main,
+        &["i"],
+    ),
+    info(Action::ViewDiff, "view-diff", Context::Main, &["v"]),
+    info(Action::Search, "search", Context::Main, &["/"]),
+    info(Action::Filter, "filter", Context::Main, &["F"]),
+    info(Action::NextMatch, "next-match", Context::Main, &["n"]),
+    info(
+        Action::PreviousMatch,
+        "previous-match",
+        Context::Main,
+        &["N"],
+    ),
+    info(Action::Autosquash, "autosquash", Context::Main, &["a"]),
+    info(Action::InsertExec, "insert-exec", Context::Main, &["X"]),
+    info(Action::EditMessage, "edit-message", Context::Main, &["m"]),
+    info(Action::Split, "split", Context::Main, &["S"]),
+    info(Action::EditAsText, "edit-as-text", Context::Main, &["E"]),
+    info(Action::ToggleFold, "toggle-fold", Context::Main, &["z"]),
+    info(Action::DiffClose, "diff-close", Context::Diff, &["esc"]),
+    info(
+        Action::DiffToggleWordDiff,
+        "diff-toggle-word-diff",
+        Context::Diff,
+        &["w"],
+    ),
+    info(
+        Action::DiffNextFile,
+        "diff-next-file",
+        Context::Diff,
+        &["tab"],
+    ),
+    info(
+        Action::DiffPreviousFile,
+        "diff-previous-file",
+        Context::Diff,
+        &["shift+tab"],
+    ),
+    info(
+        Action::DiffNextHunk,
+        "diff-next-hunk",
+        Context::Diff,
+        &["n"],
+    ),
+    info(
+        Action::DiffPreviousHunk,
+        "diff-previous-hunk",
+        Context::Diff,
+        &["p"],
+    ),
+    info(Action::DiffUp, "diff-up", Context::Diff, &["up"]),
+    info(Action::DiffDown, "diff-down", Context::Diff, &["down"]),
+    info(
+        Action::DiffPageUp,
+        "diff-page-up",
+        Context::Diff,
+        &["pageup"],
+    ),
+    info(
+        Action::DiffPageDown,
+        "diff-page-down",
+        Context::Diff,
+        &["pagedown"],
+    ),
+    info(Action::DiffTop, "diff-top", Context::Diff, &["home"]),
+    info(Action::DiffBottom, "diff-bottom", Context::Diff, &["end"]),
+];
+
+impl Action {
+    fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|info| info.name == name)
+            .map(|info| info.action)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// The shift of letters and of back tab is part of the code, not of the modifiers.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyBinding { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        KeyBinding::normalized(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    /// Parses keys like `ctrl+up`, `alt+k`, `K`, `shift+tab` or `space`.
+    fn from_str(text: &str) -> Result<Self> {
+        let (modifier_names, name) = match text.rsplit_once('+') {
+            Some((modifiers, "")) => (modifiers.strip_suffix('+').unwrap_or(modifiers), "+"),
+            Some((modifiers, name)) => (modifiers, name),
+            None => ("", text),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_names.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{modifier}' in key '{text}'"),
+            };
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(char), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(char.to_ascii_uppercase())
+            }
+            (Some(char), None) => KeyCode::Char(char),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("unknown key '{text}'"),
+                },
+            },
+        };
+
+        Ok(KeyBinding::normalized(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "CTRL+"),
+            (KeyModifiers::ALT, "ALT+"),
+            (KeyModifiers::SHIFT, "SHIFT+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::Enter => f.write_str("ENTER"),
+            KeyCode::Esc => f.write_str("ESC"),
+            KeyCode::Tab => f.write_str("TAB"),
+            KeyCode::BackTab => f.write_str("SHIFT+TAB"),
+            KeyCode::Delete => f.write_str("DELETE"),
+            KeyCode::Insert => f.write_str("INSERT"),
+            KeyCode::PageUp => f.write_str("PAGEUP"),
+            KeyCode::PageDown => f.write_str("PAGEDOWN"),
+            KeyCode::Home => f.write_str("HOME"),
+            KeyCode::End => f.write_str("END"),
+            KeyCode::Backspace => f.write_str("BACKSPACE"),
+            KeyCode::Char(' ') => f.write_str("SPACE"),
+            // Terminals do not tell the case of control characters apart
+            KeyCode::Char(char) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                write!(f, "{}", char.to_ascii_uppercase())
+            }
+            KeyCode::Char(char) => write!(f, "{char}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// The key bindings of the actions: the defaults, overridden by the keys file and then by
+/// the `interactive-rebase-editor.key.<action>` git configuration.
+pub struct Keys {
+    bindings: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .map(|info| {
+                let keys = info
+                    .default_keys
+                    .iter()
+                    .map(|key| key.parse().expect("invalid default key"))
+                    .collect();
+                (info.action, keys)
+            })
+            .collect();
+        Keys { bindings }
+    }
+}
+
+impl Keys {
+    pub fn load(git: &Git) -> Result<Self> {
+        let mut keys = Keys::default();
+
+        if let Some(path) = keys_file_path() {
+            match fs::read_to_string(&path) {
+                Ok(text) => keys
+                    .override_from_toml(&text)
+                    .with_context(|| format!("invalid keys file {}", path.display()))?,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(error).with_context(|| format!("reading {}", path.display()))
+                }
+            }
+        }
+
+        let mut overrides: HashMap<String, Vec<String>> = HashMap::new();
+        for (key, value) in git.config_regexp(r"^interactive-rebase-editor\.key\.") {
+            let name = key.rsplit('.').next().unwrap_or_default().to_string();
+            let entry = overrides.entry(name).or_default();
+            entry.extend(value.split_whitespace().map(str::to_string));
+        }
+        for (name, values) in overrides {
+            keys.override_action(&name, &values)
+                .context("invalid interactive-rebase-editor.key configuration")?;
+        }
+
+        Ok(keys)
+    }
+
+    /// Each entry maps an action name to a key or to an array of keys.
+    fn override_from_toml(&mut self, text: &str) -> Result<()> {
+        let table: toml::Table = text.parse()?;
+        for (name, value) in table {
+            let values = match value {
+                toml::Value::String(key) => vec![key],
+                toml::Value::Array(keys) => keys
+                    .into_iter()
+                    .map(|key| match key {
+                        toml::Value::String(key) => Ok(key),
+                        _ => Err(anyhow!("the keys of '{name}' must be strings")),
+                    })
+                    .collect::<Result<_>>()?,
+                _ => bail!("'{name}' must be a key or an array of keys"),
+            };
+            self.override_action(&name, &values)?;
+        }
+        Ok(())
+    }
+
+    fn override_action(&mut self, name: &str, keys: &[String]) -> Result<()> {
+        let action = Action::from_name(name).ok_or_else(|| anyhow!("unknown action '{name}'"))?;
+        let keys = keys.iter().map(|key| key.parse()).collect::<Result<_>>()?;
+        self.bindings.insert(action, keys);
+        Ok(())
+    }
+
+    /// The actions of the context bound to the key, in order of precedence.
+    ///
+    /// Letters are case-insensitive unless the upper case letter is bound by itself.
+    pub fn actions(&self, context: Context, key: KeyEvent) -> Vec<Action> {
+        let binding = KeyBinding::from(key);
+        let actions = self.bound_actions(context, binding);
+        match binding.code {
+            KeyCode::Char(char) if actions.is_empty() && char.is_ascii_uppercase() => self
+                .bound_actions(
+                    context,
+                    KeyBinding {
+                        code: KeyCode::Char(char.to_ascii_lowercase()),
+                        ..binding
+                    },
+                ),
+            _ => actions,
+        }
+    }
+
+    fn bound_actions(&self, context: Context, binding: KeyBinding) -> Vec<Action> {
+        ACTIONS
+            .iter()
+            .filter(|info| info.context == context && self.bindings(info.action).contains(&binding))
+            .map(|info| info.action)
+            .collect()
+    }
+
+    pub fn bindings(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+fn keys_file_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(
+        config_dir
+            .join("git-interactive-rebase-editor")
+            .join("keys.toml"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, Context, KeyBinding, Keys};
+
+    #[test]
+    fn parse_and_display() {
+        for (text, display) in [
+            ("ctrl+up", "CTRL+↑"),
+            ("ctrl+r", "CTRL+R"),
+            ("Alt+k", "ALT+k"),
+            ("shift+k", "K"),
+            ("shift+tab", "SHIFT+TAB"),
+            ("space", "SPACE"),
+            ("ctrl++", "CTRL++"),
+            ("F5", "F5"),
+        ] {
+            let binding: KeyBinding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), display);
+        }
+
+        assert!("hyper+k".parse::<KeyBinding>().is_err());
+        assert!("nope".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        let mut keys = Keys::default();
+        keys.override_from_toml("move-up = \"alt+k\"\nmove-down = [\"alt+j\", \"J\"]\n")
+            .unwrap();
+
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            keys.actions(Context::Main, key(KeyCode::Up, KeyModifiers::CONTROL)),
+            []
+        );
+        assert_eq!(
+            keys.actions(Context::Main, key(KeyCode::Char('k'), KeyModifiers::ALT)),
+            [Action::MoveUp]
+        );
+        assert_eq!(
+            keys.actions(Context::Main, key(KeyCode::Char('J'), KeyModifiers::SHIFT)),
+            [Action::MoveDown]
+        );
+        // Unbound upper case letters fall back to the lower case binding
+        assert_eq!(
+            keys.actions(Context::Main, key(KeyCode::Char('Q'), KeyModifiers::SHIFT)),
+            [Action::Quit]
+        );
+        assert_eq!(
+            keys.actions(Context::Main, key(KeyCode::Esc, KeyModifiers::NONE)),
+            [Action::ClearSelection, Action::Quit]
+        );
+        assert_eq!(
+            keys.actions(Context::Diff, key(KeyCode::Esc, KeyModifiers::NONE)),
+            [Action::DiffClose]
+        );
+
+        assert!(keys.override_from_toml("frobnicate = \"f\"").is_err());
+        assert!(keys.override_from_toml("quit = 1").is_err());
+    }
+}
@@ -1,32 +1,77 @@
 mod app;
+mod autosquash;
+mod batch;
+mod cli;
+mod config;
+mod diff;
+mod editor;
+mod fixup_tree;
+mod git;
+mod history;
+mod keys;
+mod message;
+mod mouse;
+mod reorder;
+mod search;
+mod session;
+mod split;
+mod todo;
+mod todo_file;
 mod tui;
 mod ui;
+mod validation;
+mod vim;
 mod widgets;
 
-use std::{env, fs, io};
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use app::{App, EditingWhat, Mode, RebaseConfirmation};
+use cli::Command;
+use config::{Config, Keymap};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, DisableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
+use git::Git;
+use keys::{Action, Context};
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
+use session::Session;
+use todo_file::TodoFile;
 use tui::Tui;
 
 use crate::ui::ui;
 
 fn main() -> Result<()> {
-    let path = {
-        let mut args = env::args();
-        args.next();
-        args.next().unwrap()
+    let path = match cli::parse(env::args_os().skip(1))? {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Command::Split => return split::split(&Git::new(".")),
+        Command::Apply { script_path, path } => return apply(&script_path, &path),
+        Command::Edit { path } => path,
     };
-    let todo_list = fs::read_to_string(&path)?;
+    let todo_file = TodoFile::read(&path)?;
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return edit_without_terminal(&path);
+    }
+
+    let todo_list = todo_file.text();
+    let git = Git::new(path.parent().unwrap_or(Path::new(".")));
+    let config = Config::load(&git)?;
 
     let backend = CrosstermBackend::new(io::stdout());
     let terminal = Terminal::new(backend)?;
@@ -35,99 +80,327 @@ fn main() -> Result<()> {
     tui.enter()?;
     setup_panic_hook();
 
-    let mut app = App::new(&todo_list);
-    let rebase_confirmation = run_app(&mut tui.terminal, &mut app);
+    let mut app = App::new(todo_list, config, git);
+    let session = Session::new(&path, todo_list, &app.git);
+    if let Some(interrupted) = session.load_interrupted() {
+        app.offer_session_restore(&interrupted);
+    }
+    app.session = Some(session);
+    let rebase_confirmation = run_app(&mut tui, &mut app);
 
     tui.reset()?;
 
-    let todo_list = if rebase_confirmation?.0 {
+    // On errors, the todo file is left as is, and the session is kept to be restored
+    let new_todo_list = if rebase_confirmation?.0 {
+        if let Some(plans) = app.get_split_plans_string()? {
+            fs::write(path.with_file_name(split::PLANS_FILE), plans)?;
+        }
         app.get_todo_list_string()?
     } else {
         String::new()
     };
-    fs::write(path, todo_list)?;
+    todo_file.write(&new_todo_list)?;
+    if let Some(session) = &app.session {
+        session.remove()?;
+    }
 
     Ok(())
 }
 
+fn apply(script_path: &Path, path: &Path) -> Result<()> {
+    let script = fs::read_to_string(script_path)
+        .with_context(|| format!("Cannot read the script from {}", script_path.display()))?;
+    let todo_file = TodoFile::read(path)?;
+    let git = Git::new(path.parent().unwrap_or(Path::new(".")));
+    let config = Config::load(&git)?;
+    let mut app = App::new(todo_file.text(), config, git);
+    batch::apply_script(&mut app, &script)?;
+    todo_file.write(&app.get_todo_list_string()?)
+}
+
+/// Without a terminal, the todo file is passed to the editor git would use, as it would be
+/// without this one, or left as is.
+fn edit_without_terminal(path: &Path) -> Result<()> {
+    let own_name = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_name().map(|name| name.to_os_string()));
+    let editor = ["GIT_EDITOR", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| {
+            let program = editor.split_whitespace().next().unwrap_or_default();
+            !program.is_empty() && Path::new(program).file_name() != own_name.as_deref()
+        });
+    let Some(editor) = editor else {
+        eprintln!("Not running in a terminal, the todo list is left unchanged");
+        return Ok(());
+    };
+
+    editor::run(&editor, path)
+}
+
 fn setup_panic_hook() {
     let panic_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic| {
         disable_raw_mode().unwrap();
-        execute!(io::stdout(), LeaveAlternateScreen).unwrap();
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture).unwrap();
 
         panic_hook(panic);
     }));
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<RebaseConfirmation> {
+fn run_app<B: Backend + Write>(tui: &mut Tui<B>, app: &mut App) -> Result<RebaseConfirmation> {
     loop {
-        terminal.draw(|f| {
+        app.save_session();
+        app.update_shown_lines();
+        tui.terminal.draw(|f| {
             ui(f, app);
         })?;
 
-        if let Event::Key(mut key) = event::read()? {
-            if key.kind != event::KeyEventKind::Press {
+        let mut key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(event) => {
+                if let Some(confirmation) = mouse::handle_mouse(app, event) {
+                    return Ok(confirmation);
+                }
                 continue;
             }
+            _ => continue,
+        };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
 
-            if let KeyCode::Char(mut char) = key.code {
+        // Letters are case-insensitive, except in typed text and where the key table or the
+        // vim keymap decide it
+        let case_insensitive = match &app.mode {
+            Mode::Editing { what } => matches!(what, EditingWhat::Command(_)),
+            Mode::ShowingOriginal { .. }
+            | Mode::ShowingProblems { .. }
+            | Mode::PreviewingAutosquash { .. }
+            | Mode::PlanningSplit { .. }
+            | Mode::RestoringSession { .. }
+            | Mode::Quitting { .. } => true,
+            Mode::Main
+            | Mode::ShowingDiff { .. }
+            | Mode::Searching { .. }
+            | Mode::EditingMessage { .. }
+            | Mode::InsertingExec { .. } => false,
+        };
+        if let KeyCode::Char(mut char) = key.code {
+            if case_insensitive {
                 char.make_ascii_lowercase();
                 key.code = KeyCode::Char(char);
             }
+        }
 
-            match &mut app.mode {
-                Mode::Main => match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') => app.ask_rebase_confirmation(),
-                    KeyCode::Insert => app.insert_todo_item(),
-                    KeyCode::Char('o') => app.show_original_todo_list(),
-                    _ if app.todo_list.items().is_empty() => {}
+        if let (Mode::Main, Keymap::Vim) = (&app.mode, app.config.keymap) {
+            if vim::handle_key(app, key) {
+                continue;
+            }
+        }
 
-                    // === Actions below are available only if the list is not empty ===
-                    KeyCode::Up if key.modifiers == KeyModifiers::CONTROL => {
-                        app.move_todo_item_up()
-                    }
-                    KeyCode::Down if key.modifiers == KeyModifiers::CONTROL => {
-                        app.move_todo_item_down()
-                    }
-                    KeyCode::PageUp => app.todo_list.select_up(app.page_length - 1),
-                    KeyCode::PageDown => app.todo_list.select_down(app.page_length - 1),
-                    KeyCode::Enter => app.enter_edition(),
-                    KeyCode::Delete => app.remove_todo_item(),
-                    KeyCode::Char('2') => app.duplicate_todo_item(),
-                    _ => app.todo_list.input(key),
-                },
+        match &mut app.mode {
+            Mode::Main => {
+                let actions = app.config.keys.actions(Context::Main, key);
+                match actions.into_iter().find(|a| is_available(app, *a)) {
+                    Some(Action::EditAsText) => edit_as_text(tui, app)?,
+                    Some(action) => run_main_action(app, action),
+                    None => {}
+                }
+            }
 
-                Mode::Editing { what, .. } => match key.code {
-                    KeyCode::Esc => app.cancel_edition(),
-                    KeyCode::Enter => app.confirm_edition(),
-                    KeyCode::Tab => app.switch_edition(),
-                    KeyCode::Char(char) => {
-                        if let EditingWhat::Command(command) = what {
-                            App::select_command_by_char(command, char);
-                        }
-                    }
-                    _ => match what {
-                        EditingWhat::Command(commands) => commands.input(key),
-                        EditingWhat::Parameters(parameters) => parameters.input(key),
+            Mode::Editing { what, .. } => match key.code {
+                KeyCode::Esc => app.cancel_edition(),
+                KeyCode::Enter => app.confirm_edition(),
+                KeyCode::Tab => app.switch_edition(),
+                _ => match what {
+                    EditingWhat::Command(commands) => match key.code {
+                        KeyCode::Char(char) => App::select_command_by_char(commands, char),
+                        _ => commands.input(key),
                     },
+                    EditingWhat::Parameters(parameters) => parameters.input(key),
                 },
+            },
 
-                Mode::ShowingOriginal { scroll } => match key.code {
-                    KeyCode::Esc => app.mode = Mode::Main,
-                    KeyCode::Up => *scroll = scroll.saturating_sub(1),
-                    KeyCode::Down => *scroll = scroll.saturating_add(1),
-                    _ => {}
-                },
+            Mode::Searching { input, .. } => match key.code {
+                KeyCode::Esc => app.cancel_search(),
+                KeyCode::Enter => app.confirm_search(),
+                _ => {
+                    input.input(key);
+                    app.update_search();
+                }
+            },
 
-                Mode::Quitting(rebase_confirmation) => match key.code {
-                    KeyCode::Esc => app.mode = Mode::Main,
-                    KeyCode::Char('y') => return Ok(RebaseConfirmation(true)),
-                    KeyCode::Char('n') => return Ok(RebaseConfirmation(false)),
-                    KeyCode::Enter => return Ok(*rebase_confirmation.selected_item()),
-                    _ => rebase_confirmation.input(key),
-                },
+            Mode::EditingMessage { input, .. } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.confirm_message_edition()
+                }
+                _ => input.input(key),
+            },
+
+            Mode::PlanningSplit { files, .. } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Enter => app.confirm_split_plan(),
+                KeyCode::Char(char @ '1'..='9') => {
+                    files.selected_item_mut().group = char as usize - '0' as usize;
+                    files.select_down(1);
+                }
+                _ => files.input(key),
+            },
+
+            Mode::InsertingExec { input, .. } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Enter => app.confirm_exec_insertion(),
+                _ => input.input(key),
+            },
+
+            Mode::ShowingOriginal { scroll } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                _ => {}
+            },
+
+            Mode::RestoringSession { scroll, .. } => match key.code {
+                KeyCode::Char('y') => app.restore_session(),
+                KeyCode::Char('n') | KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                _ => {}
+            },
+
+            Mode::PreviewingAutosquash { scroll, .. } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Enter => app.apply_autosquash(),
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                _ => {}
+            },
+
+            Mode::ShowingDiff { diff, scroll } => {
+                let line = *scroll as usize;
+                let jump_to = |position: Option<usize>| position.map_or(line, |p| p) as u16;
+                let actions = app.config.keys.actions(Context::Diff, key);
+                match actions.first() {
+                    Some(Action::DiffClose) => app.mode = Mode::Main,
+                    Some(Action::DiffToggleWordDiff) => app.toggle_word_diff(),
+                    Some(Action::DiffNextFile) => *scroll = jump_to(diff.next_file(line)),
+                    Some(Action::DiffPreviousFile) => *scroll = jump_to(diff.previous_file(line)),
+                    Some(Action::DiffNextHunk) => *scroll = jump_to(diff.next_hunk(line)),
+                    Some(Action::DiffPreviousHunk) => *scroll = jump_to(diff.previous_hunk(line)),
+                    Some(Action::DiffUp) => *scroll = scroll.saturating_sub(1),
+                    Some(Action::DiffDown) => *scroll = scroll.saturating_add(1),
+                    Some(Action::DiffPageUp) => {
+                        *scroll = scroll.saturating_sub(app.page_length as u16)
+                    }
+                    Some(Action::DiffPageDown) => {
+                        *scroll = scroll.saturating_add(app.page_length as u16)
+                    }
+                    Some(Action::DiffTop) => *scroll = 0,
+                    Some(Action::DiffBottom) => *scroll = u16::MAX,
+                    _ => {}
+                }
             }
+
+            Mode::ShowingProblems {
+                problems,
+                can_proceed,
+            } => match key.code {
+                KeyCode::Enter if *can_proceed => app.confirm_rebase(),
+                KeyCode::Esc | KeyCode::Enter => app.mode = Mode::Main,
+                KeyCode::Char('n') => return Ok(RebaseConfirmation(false)),
+                _ => {
+                    problems.input(key);
+                    app.todo_list.select(problems.selected_item().index);
+                }
+            },
+
+            Mode::Quitting { confirmation, .. } => match key.code {
+                KeyCode::Esc => app.mode = Mode::Main,
+                KeyCode::Char('y') if confirmation.items().contains(&RebaseConfirmation(true)) => {
+                    return Ok(RebaseConfirmation(true))
+                }
+                KeyCode::Char('n') => return Ok(RebaseConfirmation(false)),
+                KeyCode::Enter => return Ok(*confirmation.selected_item()),
+                _ => confirmation.input(key),
+            },
+        }
+    }
+}
+
+/// Suspends the interface while the list is edited as text in the editor git uses for commit
+/// messages. A failing editor, like vim quitting with `:cq`, leaves the list unchanged.
+fn edit_as_text<B: Backend + Write>(tui: &mut Tui<B>, app: &mut App) -> Result<()> {
+    let todo_list = app.get_todo_list_string()?;
+    let editor = app.git.editor().unwrap_or_else(|| "vi".to_string());
+    tui.reset()?;
+    let edited = editor::edit_text(&editor, "git-rebase-todo", &todo_list);
+    tui.enter()?;
+    if let Ok(edited) = edited {
+        app.replace_todo_list(&edited)?;
+    }
+    Ok(())
+}
+
+fn is_available(app: &App, action: Action) -> bool {
+    match action {
+        Action::ClearSelection => app.todo_list.has_multi_selection(),
+        Action::Quit
+        | Action::Insert
+        | Action::ShowOriginal
+        | Action::Undo
+        | Action::Redo
+        | Action::Search
+        | Action::Filter
+        | Action::EditAsText => true,
+        // The other actions need some line to act on
+        _ => app.todo_list.shown_len() > 0,
+    }
+}
+
+fn run_main_action(app: &mut App, action: Action) {
+    match action {
+        Action::ClearSelection => app.todo_list.clear_multi_selection(),
+        Action::Quit => app.ask_rebase_confirmation(),
+        Action::Insert => app.insert_todo_item(),
+        Action::ShowOriginal => app.show_original_todo_list(),
+        Action::Undo => app.undo(),
+        Action::Redo => app.redo(),
+        Action::MoveUp => app.move_todo_item_up(),
+        Action::MoveDown => app.move_todo_item_down(),
+        Action::ExtendUp => {
+            app.todo_list.start_range();
+            app.todo_list.select_up(1);
+        }
+        Action::ExtendDown => {
+            app.todo_list.start_range();
+            app.todo_list.select_down(1);
         }
+        Action::Up => app.todo_list.select_up(1),
+        Action::Down => app.todo_list.select_down(1),
+        Action::First => app.todo_list.select_first(),
+        Action::Last => app.todo_list.select_last(),
+        Action::PageUp => app.todo_list.select_up(app.page_length - 1),
+        Action::PageDown => app.todo_list.select_down(app.page_length - 1),
+        Action::Mark => app.todo_list.toggle_mark(),
+        Action::SelectRange => app.todo_list.toggle_range(),
+        Action::Edit => app.enter_edition(),
+        Action::Remove => app.remove_todo_item(),
+        Action::Duplicate => app.duplicate_todo_item(),
+        Action::ToggleDropOnRemove => app.toggle_drop_on_remove(),
+        Action::ToggleDetails => app.toggle_commit_details(),
+        Action::ViewDiff => app.show_diff(),
+        Action::Search => app.start_search(false),
+        Action::Filter => app.start_search(true),
+        Action::NextMatch => app.next_match(),
+        Action::PreviousMatch => app.previous_match(),
+        Action::Autosquash => app.preview_autosquash(),
+        Action::InsertExec => app.start_exec_insertion(),
+        Action::EditMessage => app.start_message_edition(),
+        Action::Split => app.start_split_planning(),
+        Action::ToggleFold => app.toggle_fold(),
+        _ => {}
     }
 }
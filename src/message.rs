@@ -0,0 +1,74 @@
+const PREFIX: &str = "printf '%s\\n' ";
+const SUFFIX: &str = " | git commit --amend -F -";
+
+/// The command of an `exec` line amending the commit with `message`, so that git does not
+/// stop to ask for it. A todo line cannot contain line breaks, so the message is passed as
+/// one `printf` argument per line:
+///
+/// ```text
+/// exec printf '%s\n' 'Subject' '' 'Body' | git commit --amend -F -
+/// ```
+pub fn amend_command(message: &str) -> String {
+    let arguments: Vec<_> = message.lines().map(quote).collect();
+    format!("{PREFIX}{}{SUFFIX}", arguments.join(" "))
+}
+
+/// The message of a command made by [`amend_command`].
+pub fn parse_amend_command(command: &str) -> Option<String> {
+    let mut rest = command.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    let mut lines = Vec::new();
+    loop {
+        let (line, after) = unquote(rest)?;
+        lines.push(line);
+        match after.strip_prefix(' ') {
+            Some(after) => rest = after,
+            None if after.is_empty() => break,
+            None => return None,
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+/// Quotes `str` for the shell, which takes everything literally between single quotes.
+fn quote(str: &str) -> String {
+    format!("'{}'", str.replace('\'', r"'\''"))
+}
+
+/// Reads a word quoted by [`quote`] at the start of `str`, and returns it with what follows.
+fn unquote(mut str: &str) -> Option<(String, &str)> {
+    let mut word = String::new();
+    loop {
+        if let Some(rest) = str.strip_prefix(r"\'") {
+            word.push('\'');
+            str = rest;
+        } else if let Some(rest) = str.strip_prefix('\'') {
+            let (quoted, rest) = rest.split_once('\'')?;
+            word.push_str(quoted);
+            str = rest;
+        } else {
+            return Some((word, str));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{amend_command, parse_amend_command};
+
+    #[test]
+    fn round_trip() {
+        let message = "Don't panic\n\nIt's fine:\n  'quoted' $HOME `ls` \"x\"";
+        let command = amend_command(message);
+        assert_eq!(
+            command,
+            r#"printf '%s\n' 'Don'\''t panic' '' 'It'\''s fine:' '  '\''quoted'\'' $HOME `ls` "x"' | git commit --amend -F -"#
+        );
+        assert_eq!(parse_amend_command(&command).unwrap(), message);
+
+        assert_eq!(parse_amend_command("make test"), None);
+        assert_eq!(
+            parse_amend_command(r"printf '%s\n' 'a' b | git commit --amend -F -"),
+            None
+        );
+    }
+}
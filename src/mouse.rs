@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+
+use crate::app::{App, EditingWhat, Mode, RebaseConfirmation};
+
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const WHEEL_LINES: u16 = 3;
+
+#[derive(Default)]
+pub struct Mouse {
+    /// When and on which line the last click happened, to detect double clicks.
+    last_click: Option<(Instant, usize)>,
+}
+
+/// Returns the choice if one was clicked in the quitting dialog.
+pub fn handle_mouse(app: &mut App, event: MouseEvent) -> Option<RebaseConfirmation> {
+    let click = event.kind == MouseEventKind::Down(MouseButton::Left);
+    match &mut app.mode {
+        Mode::Main => handle_main_mouse(app, event),
+
+        Mode::Editing {
+            what: EditingWhat::Command(commands),
+        } if click => {
+            let area = app.screen_areas.commands;
+            if let Some(index) =
+                clicked_index(area, commands.offset(), commands.items().len(), event)
+            {
+                commands.select(index);
+                app.confirm_edition();
+            }
+        }
+
+        Mode::ShowingOriginal { scroll }
+        | Mode::ShowingDiff { scroll, .. }
+        | Mode::PreviewingAutosquash { scroll, .. }
+        | Mode::RestoringSession { scroll, .. } => match event.kind {
+            MouseEventKind::ScrollUp => *scroll = scroll.saturating_sub(WHEEL_LINES),
+            MouseEventKind::ScrollDown => *scroll = scroll.saturating_add(WHEEL_LINES),
+            _ => {}
+        },
+
+        Mode::Quitting { confirmation, .. } if click => {
+            let area = app.screen_areas.confirmation;
+            let items = confirmation.items();
+            if let Some(index) = clicked_index(area, confirmation.offset(), items.len(), event) {
+                return Some(items[index]);
+            }
+        }
+
+        _ => {}
+    }
+    None
+}
+
+fn handle_main_mouse(app: &mut App, event: MouseEvent) {
+    let area = app.screen_areas.todo_list;
+    let len = app.todo_list.shown_len();
+    if len == 0 {
+        return;
+    }
+
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            let Some(index) = clicked_index(area, app.todo_list.offset(), len, event)
+                .and_then(|position| app.todo_list.index_at(position))
+            else {
+                return;
+            };
+
+            let double_click = matches!(
+                app.mouse.last_click,
+                Some((time, line)) if line == index && time.elapsed() < DOUBLE_CLICK_INTERVAL
+            );
+            if double_click {
+                app.mouse.last_click = None;
+                app.enter_edition();
+            } else {
+                app.mouse.last_click = Some((Instant::now(), index));
+                app.todo_list.clear_multi_selection();
+                app.todo_list.select(index);
+                app.start_drag();
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) if area.height > 0 => {
+            // Dragging beyond the list moves the line to the first or last visible position
+            let row = event.row.clamp(area.top(), area.bottom() - 1);
+            let position = app.todo_list.offset() + (row - area.top()) as usize;
+            if let Some(index) = app.todo_list.index_at(position.min(len - 1)) {
+                app.drag_to(index);
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) => app.end_drag(),
+        MouseEventKind::ScrollUp => app.todo_list.select_up(WHEEL_LINES as usize),
+        MouseEventKind::ScrollDown => app.todo_list.select_down(WHEEL_LINES as usize),
+        _ => {}
+    }
+}
+
+/// The position in the rendered list of the item under the mouse, if any.
+fn clicked_index(area: Rect, offset: usize, len: usize, event: MouseEvent) -> Option<usize> {
+    let inside = event.column >= area.left()
+        && event.column < area.right()
+        && event.row >= area.top()
+        && event.row < area.bottom();
+    let index = offset + (event.row.checked_sub(area.top())?) as usize;
+    (inside && index < len).then_some(index)
+}
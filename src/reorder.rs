@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+/// Finds the commits that were moved before commits they originally came after, while
+/// touching some of the same files. Such reorderings are likely to conflict.
+///
+/// Returns, for each of those commits, the commits it was moved before.
+pub fn predict_conflicts<'h>(
+    original_order: &[&'h str],
+    current_order: &[&'h str],
+    files: impl Fn(&str) -> Option<&'h [String]>,
+) -> HashMap<String, Vec<String>> {
+    let original_position = |hash: &str| original_order.iter().position(|h| *h == hash);
+
+    let mut conflicts: HashMap<String, Vec<String>> = HashMap::new();
+    for (i, earlier) in current_order.iter().enumerate() {
+        let (Some(earlier_position), Some(earlier_files)) =
+            (original_position(earlier), files(earlier))
+        else {
+            continue;
+        };
+
+        for later in &current_order[i + 1..] {
+            let (Some(later_position), Some(later_files)) =
+                (original_position(later), files(later))
+            else {
+                continue;
+            };
+
+            if earlier_position > later_position
+                && earlier_files.iter().any(|file| later_files.contains(file))
+            {
+                let others = conflicts.entry(earlier.to_string()).or_default();
+                if !others.iter().any(|other| other == later) {
+                    others.push(later.to_string());
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::predict_conflicts;
+
+    #[test]
+    fn moved_commits_touching_same_files() {
+        let files: HashMap<&str, Vec<String>> = [
+            ("a", vec!["x.rs".to_string()]),
+            ("b", vec!["y.rs".to_string()]),
+            ("c", vec!["x.rs".to_string(), "z.rs".to_string()]),
+            ("d", vec!["x.rs".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+        let files = |hash: &str| files.get(hash).map(Vec::as_slice);
+
+        let original = ["a", "b", "c"];
+
+        assert!(predict_conflicts(&original, &original, files).is_empty());
+        assert!(predict_conflicts(&original, &["b", "a", "c"], files).is_empty());
+
+        let conflicts = predict_conflicts(&original, &["c", "a", "b"], files);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts["c"], ["a"]);
+
+        // Commits that were not in the original list are not checked
+        assert!(predict_conflicts(&original, &["d", "a", "c"], files).is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+/// A search whose matches are highlighted, or when filtering, the only lines shown.
+pub struct Search {
+    pub query: String,
+    pub filter: bool,
+}
+
+/// Whether each word of the query is found in some of the fields, case-insensitively and
+/// with its characters in order but not necessarily together.
+pub fn fuzzy_matches<'f>(query: &str, fields: impl IntoIterator<Item = &'f str> + Clone) -> bool {
+    query.split_whitespace().all(|word| {
+        fields
+            .clone()
+            .into_iter()
+            .any(|field| is_subsequence(word, field))
+    })
+}
+
+fn is_subsequence(word: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .all(|char| text.any(|other| other == char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_matches;
+
+    #[test]
+    fn fuzzy() {
+        let fields = [
+            "1a2b3c Fix the parser",
+            "Jane Doe <jane@example.com>",
+            "src/parser.rs",
+        ];
+
+        assert!(fuzzy_matches("", fields));
+        assert!(fuzzy_matches("1a2b", fields));
+        assert!(fuzzy_matches("fix parser", fields));
+        assert!(fuzzy_matches("fxprsr", fields));
+        assert!(fuzzy_matches("JANE src/pars", fields));
+        assert!(!fuzzy_matches("rsparser", fields));
+        assert!(!fuzzy_matches("fix lexer", fields));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::git::Git;
+
+/// The extension of the journal, added to the name of the todo file.
+const EXTENSION: &str = "gire-session";
+
+/// A journal of the todo list being edited, saved after every change so that the arrangement
+/// can be restored if the editor does not exit normally.
+pub struct Session {
+    path: PathBuf,
+    /// The todo list the session started from.
+    original: String,
+    /// The todo list last saved.
+    saved: String,
+}
+
+impl Session {
+    /// The journal is kept in the git directory, since the directory of the todo file is
+    /// deleted when the rebase is aborted.
+    pub fn new(todo_path: &Path, original: &str, git: &Git) -> Self {
+        let name = format!(
+            "{}.{EXTENSION}",
+            todo_path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let path = git
+            .git_path(&name)
+            .unwrap_or_else(|| todo_path.with_file_name(&name));
+        Session {
+            path,
+            original: original.to_string(),
+            saved: original.to_string(),
+        }
+    }
+
+    /// The todo list of an interrupted session that started from the same todo list, if it
+    /// was changed.
+    pub fn load_interrupted(&self) -> Option<String> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        let (original, current) = parse(&text).ok()?;
+        (original == self.original && current != self.original).then_some(current)
+    }
+
+    /// Saves the todo list if it changed since the last time.
+    pub fn save(&mut self, todo_list: &str) -> Result<()> {
+        if todo_list == self.saved {
+            return Ok(());
+        }
+
+        let mut table = toml::Table::new();
+        table.insert("original".to_string(), self.original.clone().into());
+        table.insert("current".to_string(), todo_list.to_string().into());
+        fs::write(&self.path, table.to_string())
+            .with_context(|| format!("Cannot save the session to {}", self.path.display()))?;
+        self.saved = todo_list.to_string();
+        Ok(())
+    }
+
+    /// Deletes the journal, once the editor exits normally.
+    pub fn remove(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The original and current todo lists of a journal.
+fn parse(text: &str) -> Result<(String, String)> {
+    let table: toml::Table = text.parse()?;
+    let field = |name: &str| {
+        table
+            .get(name)
+            .and_then(toml::Value::as_str)
+            .map(str::to_string)
+            .with_context(|| format!("No {name} todo list in the session"))
+    };
+    Ok((field("original")?, field("current")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::git::Git;
+
+    use super::Session;
+
+    #[test]
+    fn interrupted_session() {
+        let dir = env::temp_dir().join(format!("gire-session-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let todo_path = dir.join("git-rebase-todo");
+        let original = "pick 1a1a1a A \"quoted\"\npick 2b2b2b B\n# comment\n";
+        let git = Git::new(&dir);
+
+        let mut session = Session::new(&todo_path, original, &git);
+        assert_eq!(session.load_interrupted(), None);
+        session.save(original).unwrap();
+        assert_eq!(session.load_interrupted(), None);
+
+        let changed = "pick 2b2b2b B\npick 1a1a1a A \"quoted\"\n# comment\n";
+        session.save(changed).unwrap();
+        let session = Session::new(&todo_path, original, &git);
+        assert_eq!(session.load_interrupted().as_deref(), Some(changed));
+        // Another rebase
+        let other = Session::new(&todo_path, "pick 3c3c3c C\n", &git);
+        assert_eq!(other.load_interrupted(), None);
+
+        session.remove().unwrap();
+        assert_eq!(session.load_interrupted(), None);
+        session.remove().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
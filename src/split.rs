@@ -0,0 +1,247 @@
+use std::{collections::BTreeMap, fmt::Write, fs};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::git::Git;
+
+/// The file where the plans are saved, next to the todo file.
+pub const PLANS_FILE: &str = "interactive-rebase-editor-split";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SplitFile {
+    pub path: String,
+    /// The number of the new commit the file goes to, from 1.
+    pub group: usize,
+}
+
+/// How the files of a commit are shared among new commits.
+pub type SplitPlan = Vec<SplitFile>;
+
+pub fn group_count(plan: &[SplitFile]) -> usize {
+    let mut groups: Vec<_> = plan.iter().map(|file| file.group).collect();
+    groups.sort_unstable();
+    groups.dedup();
+    groups.len()
+}
+
+/// One line per file, made of the commit hash, the group and the path.
+pub fn format_plans<'p>(
+    plans: impl IntoIterator<Item = (&'p str, &'p SplitPlan)>,
+) -> Result<String> {
+    let mut str = String::new();
+    for (hash, plan) in plans {
+        for file in plan {
+            writeln!(&mut str, "{hash} {} {}", file.group, file.path)?;
+        }
+    }
+    Ok(str)
+}
+
+/// The plans by commit hash, in the order of the file.
+pub fn parse_plans(str: &str) -> Result<Vec<(String, SplitPlan)>> {
+    let mut plans: Vec<(String, SplitPlan)> = Vec::new();
+    for line in str.lines() {
+        let mut fields = line.splitn(3, ' ');
+        let (Some(hash), Some(group), Some(path)) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("Invalid line in the split plans: {line}");
+        };
+        let file = SplitFile {
+            path: path.to_string(),
+            group: group
+                .parse()
+                .with_context(|| format!("Invalid line in the split plans: {line}"))?,
+        };
+        match plans.last_mut() {
+            Some((last_hash, plan)) if last_hash == hash => plan.push(file),
+            _ => plans.push((hash.to_string(), vec![file])),
+        }
+    }
+    Ok(plans)
+}
+
+/// Splits the commit the rebase stopped at, following the plan saved for it: the commit is
+/// undone, and its files are committed again group by group. The first new commit keeps the
+/// message of the commit, and the last one takes the other files of the commit.
+pub fn split(git: &Git) -> Result<()> {
+    let plans_path = git
+        .git_path(&format!("rebase-merge/{PLANS_FILE}"))
+        .ok_or_else(|| anyhow!("Not in a git repository"))?;
+    let plans = fs::read_to_string(&plans_path)
+        .context("No split plan was saved: mark a commit for splitting while rebasing")?;
+    let plans = parse_plans(&plans)?;
+
+    let head = git.run(&["rev-parse", "HEAD"])?.trim().to_string();
+    let original = stopped_commit(git)?;
+    let Some((_, plan)) = plans
+        .iter()
+        .find(|(hash, _)| original.starts_with(hash.as_str()))
+    else {
+        bail!("No split plan was saved for the current commit {original}");
+    };
+    let subject = git.run(&["log", "-1", "--format=%s", &head])?;
+    let author = git.run(&["log", "-1", "--format=%an <%ae>", &head])?;
+
+    let mut groups: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for file in plan {
+        groups.entry(file.group).or_default().push(&file.path);
+    }
+
+    // Only these are committed again, leaving the untracked files out
+    let touched = git.run(&["diff", "--name-only", "--no-renames", "-z", "HEAD^", &head])?;
+    let touched: Vec<&str> = touched.split_terminator('\0').collect();
+
+    git.run(&["reset", "--quiet", "HEAD^"])?;
+    let count = groups.len();
+    let mut committed = 0;
+    for (position, paths) in groups.values().enumerate() {
+        let reuse_message = ["--reuse-message", &head];
+        let message = format!("{} ({}/{count})", subject.trim_end(), position + 1);
+        let author = format!("--author={}", author.trim_end());
+        let new_message = [author.as_str(), "--message", &message];
+        let last = position + 1 == count;
+        let done = commit_files(
+            git,
+            if last { &touched } else { paths },
+            if committed == 0 {
+                &reuse_message
+            } else {
+                &new_message
+            },
+        )
+        .with_context(|| {
+            format!("Splitting failed, the commit can be restored with: git reset --hard {head}")
+        })?;
+        if done {
+            committed += 1;
+        }
+    }
+
+    println!("Split {head} into {committed} commits, continue with: git rebase --continue");
+    Ok(())
+}
+
+/// The commit the rebase stopped at, as it was in the todo list. The rebased copy at `HEAD` has
+/// another hash whenever the commits before it changed.
+fn stopped_commit(git: &Git) -> Result<String> {
+    let done_path = git
+        .git_path("rebase-merge/done")
+        .ok_or_else(|| anyhow!("Not in a git repository"))?;
+    let done = fs::read_to_string(done_path).context("Not stopped in an interactive rebase")?;
+    let Some(commit) = done
+        .lines()
+        .last()
+        .and_then(|line| line.split_whitespace().nth(1))
+    else {
+        bail!("Not stopped at a commit in the interactive rebase");
+    };
+    let commit = git.run(&["rev-parse", "--verify", &format!("{commit}^{{commit}}")])?;
+    Ok(commit.trim().to_string())
+}
+
+/// Commits the changes to the files, including their deletion. Returns `false` if there was
+/// nothing to commit.
+fn commit_files(git: &Git, paths: &[&str], commit_args: &[&str]) -> Result<bool> {
+    if !paths.is_empty() {
+        let mut add = vec!["add", "--all", "--"];
+        add.extend(paths);
+        git.run(&add)?;
+    }
+    if git.run(&["diff", "--cached", "--quiet"]).is_ok() {
+        return Ok(false);
+    }
+
+    let mut commit = vec!["commit", "--quiet"];
+    commit.extend(commit_args);
+    git.run(&commit)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::git::Git;
+
+    use super::{format_plans, group_count, parse_plans, split, SplitFile, PLANS_FILE};
+
+    #[test]
+    fn plans_round_trip() {
+        let plan = vec![
+            SplitFile {
+                path: "src/main.rs".to_string(),
+                group: 1,
+            },
+            SplitFile {
+                path: "docs/with space.md".to_string(),
+                group: 3,
+            },
+        ];
+        let other = vec![SplitFile {
+            path: "README.md".to_string(),
+            group: 2,
+        }];
+        assert_eq!(group_count(&plan), 2);
+
+        let str = format_plans([("abc123", &plan), ("def456", &other)]).unwrap();
+        assert_eq!(
+            str,
+            "abc123 1 src/main.rs\nabc123 3 docs/with space.md\ndef456 2 README.md\n"
+        );
+        assert_eq!(
+            parse_plans(&str).unwrap(),
+            vec![("abc123".to_string(), plan), ("def456".to_string(), other)]
+        );
+
+        assert!(parse_plans("abc123 one README.md").is_err());
+        assert!(parse_plans("abc123").is_err());
+    }
+
+    #[test]
+    fn split_after_reordering() {
+        let dir = env::temp_dir().join(format!("gire-split-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let git = Git::new(&dir);
+        let run = |args: &[&str]| git.run(args).unwrap().trim().to_string();
+        run(&["init", "--quiet"]);
+        run(&["config", "user.name", "Tester"]);
+        run(&["config", "user.email", "tester@example.com"]);
+        run(&["config", "commit.gpgSign", "false"]);
+        for (subject, files) in [("A", &["a"][..]), ("B", &["b"]), ("C", &["c", "d"])] {
+            for file in files {
+                fs::write(dir.join(file), subject).unwrap();
+            }
+            run(&["add", "--all"]);
+            run(&["commit", "--quiet", "--message", subject]);
+        }
+        let hashes = run(&["log", "--format=%h", "--reverse"]);
+        let [a, b, c] = hashes.lines().collect::<Vec<_>>()[..] else {
+            panic!("unexpected log: {hashes}");
+        };
+
+        // The rebased copy of C has another hash, as A and B are swapped
+        let todo = dir.join("todo");
+        fs::write(&todo, format!("pick {b} B\npick {a} A\nedit {c} C\n")).unwrap();
+        let editor = format!("sequence.editor=cp '{}'", todo.display());
+        run(&[
+            "-c",
+            &editor,
+            "rebase",
+            "--quiet",
+            "--interactive",
+            "--root",
+        ]);
+        assert_ne!(run(&["rev-parse", "--short", "HEAD"]), c);
+
+        let plans = format!("{c} 1 c\n{c} 2 d\n");
+        let plans_path = git.git_path(&format!("rebase-merge/{PLANS_FILE}")).unwrap();
+        fs::write(plans_path, plans).unwrap();
+        split(&git).unwrap();
+        assert_eq!(
+            run(&["log", "--format=%s", "--name-only"]),
+            "C (2/2)\n\nd\nC\n\nc\nA\n\na\nB\n\nb"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
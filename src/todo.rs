@@ -0,0 +1,559 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageOption {
+    /// `-C`: use the commit message as is.
+    Use,
+    /// `-c`: use the commit message, but open the editor on it.
+    Edit,
+}
+
+impl MessageOption {
+    fn parse(flag: &str) -> Option<Self> {
+        match flag {
+            "-C" => Some(MessageOption::Use),
+            "-c" => Some(MessageOption::Edit),
+            _ => None,
+        }
+    }
+
+    pub fn flag(&self) -> &'static str {
+        match self {
+            MessageOption::Use => "-C",
+            MessageOption::Edit => "-c",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CommitLine {
+    pub hash: String,
+    pub subject: String,
+}
+
+impl CommitLine {
+    fn parse(parameters: &str) -> Self {
+        let (hash, subject) = parameters.split_once(' ').unwrap_or((parameters, ""));
+        CommitLine {
+            hash: hash.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    fn format(&self) -> String {
+        if self.subject.is_empty() {
+            self.hash.clone()
+        } else {
+            format!("{} {}", self.hash, self.subject)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TodoCommand {
+    Pick(CommitLine),
+    Reword(CommitLine),
+    Edit(CommitLine),
+    Squash(CommitLine),
+    Fixup(Option<MessageOption>, CommitLine),
+    Exec(String),
+    Break,
+    Drop(CommitLine),
+    Label(String),
+    Reset {
+        label: String,
+        subject: Option<String>,
+    },
+    Merge {
+        commit: Option<(MessageOption, String)>,
+        label: String,
+        subject: Option<String>,
+    },
+    UpdateRef(String),
+    Noop,
+    /// A line that git would not accept as is.
+    Unknown {
+        command: String,
+        parameters: String,
+    },
+}
+
+impl TodoCommand {
+    pub fn parse(command: &str, parameters: &str) -> Self {
+        match command {
+            "pick" | "p" => TodoCommand::Pick(CommitLine::parse(parameters)),
+            "reword" | "r" => TodoCommand::Reword(CommitLine::parse(parameters)),
+            "edit" | "e" => TodoCommand::Edit(CommitLine::parse(parameters)),
+            "squash" | "s" => TodoCommand::Squash(CommitLine::parse(parameters)),
+            "fixup" | "f" => {
+                let (message, parameters) = match parameters.split_once(' ') {
+                    Some((flag, rest)) => match MessageOption::parse(flag) {
+                        Some(message) => (Some(message), rest),
+                        None => (None, parameters),
+                    },
+                    None => (None, parameters),
+                };
+                TodoCommand::Fixup(message, CommitLine::parse(parameters))
+            }
+            "exec" | "x" => TodoCommand::Exec(parameters.to_string()),
+            "break" | "b" if parameters.is_empty() => TodoCommand::Break,
+            "drop" | "d" => TodoCommand::Drop(CommitLine::parse(parameters)),
+            "label" | "l" => TodoCommand::Label(parameters.to_string()),
+            "reset" | "t" => {
+                let (label, subject) = split_subject(parameters);
+                TodoCommand::Reset {
+                    label: label.to_string(),
+                    subject,
+                }
+            }
+            "merge" | "m" => Self::parse_merge(parameters),
+            "update-ref" | "u" => TodoCommand::UpdateRef(parameters.to_string()),
+            "noop" if parameters.is_empty() => TodoCommand::Noop,
+            _ => TodoCommand::Unknown {
+                command: command.to_string(),
+                parameters: parameters.to_string(),
+            },
+        }
+    }
+
+    fn parse_merge(parameters: &str) -> Self {
+        let mut rest = parameters;
+        let mut commit = None;
+
+        if let Some((flag, after_flag)) = rest.split_once(' ') {
+            if let Some(message) = MessageOption::parse(flag) {
+                let (hash, after_hash) = after_flag.split_once(' ').unwrap_or((after_flag, ""));
+                commit = Some((message, hash.to_string()));
+                rest = after_hash;
+            }
+        }
+
+        let (label, subject) = split_subject(rest);
+
+        TodoCommand::Merge {
+            commit,
+            label: label.to_string(),
+            subject,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            TodoCommand::Pick(_) => "pick",
+            TodoCommand::Reword(_) => "reword",
+            TodoCommand::Edit(_) => "edit",
+            TodoCommand::Squash(_) => "squash",
+            TodoCommand::Fixup(..) => "fixup",
+            TodoCommand::Exec(_) => "exec",
+            TodoCommand::Break => "break",
+            TodoCommand::Drop(_) => "drop",
+            TodoCommand::Label(_) => "label",
+            TodoCommand::Reset { .. } => "reset",
+            TodoCommand::Merge { .. } => "merge",
+            TodoCommand::UpdateRef(_) => "update-ref",
+            TodoCommand::Noop => "noop",
+            TodoCommand::Unknown { command, .. } => command,
+        }
+    }
+
+    pub fn parameters(&self) -> String {
+        match self {
+            TodoCommand::Pick(commit)
+            | TodoCommand::Reword(commit)
+            | TodoCommand::Edit(commit)
+            | TodoCommand::Squash(commit)
+            | TodoCommand::Fixup(_, commit)
+            | TodoCommand::Drop(commit) => commit.format(),
+            TodoCommand::Exec(text) | TodoCommand::Label(text) | TodoCommand::UpdateRef(text) => {
+                text.clone()
+            }
+            TodoCommand::Reset { label, subject } => with_subject(label, subject),
+            TodoCommand::Break | TodoCommand::Noop => String::new(),
+            TodoCommand::Merge {
+                commit,
+                label,
+                subject,
+            } => {
+                let mut str = String::new();
+                if let Some((message, hash)) = commit {
+                    str.push_str(message.flag());
+                    str.push(' ');
+                    str.push_str(hash);
+                    str.push(' ');
+                }
+                str.push_str(&with_subject(label, subject));
+                str
+            }
+            TodoCommand::Unknown { parameters, .. } => parameters.clone(),
+        }
+    }
+
+    pub fn commit(&self) -> Option<&CommitLine> {
+        match self {
+            TodoCommand::Pick(commit)
+            | TodoCommand::Reword(commit)
+            | TodoCommand::Edit(commit)
+            | TodoCommand::Squash(commit)
+            | TodoCommand::Fixup(_, commit)
+            | TodoCommand::Drop(commit) => Some(commit),
+            _ => None,
+        }
+    }
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            TodoCommand::Pick(_) => "p",
+            TodoCommand::Reword(_) => "r",
+            TodoCommand::Edit(_) => "e",
+            TodoCommand::Squash(_) => "s",
+            TodoCommand::Fixup(..) => "f",
+            TodoCommand::Exec(_) => "x",
+            TodoCommand::Break => "b",
+            TodoCommand::Drop(_) => "d",
+            TodoCommand::Label(_) => "l",
+            TodoCommand::Reset { .. } => "t",
+            TodoCommand::Merge { .. } => "m",
+            TodoCommand::UpdateRef(_) => "u",
+            TodoCommand::Noop | TodoCommand::Unknown { .. } => self.name(),
+        }
+    }
+
+    /// The name, with the `-C`/`-c` option of a `fixup` command.
+    pub fn label(&self) -> String {
+        match self.message_option() {
+            Some(message) => format!("{} {}", self.name(), message.flag()),
+            None => self.name().to_string(),
+        }
+    }
+
+    /// The `-C`/`-c` option of a `fixup` command. It is not part of [`Self::parameters`].
+    pub fn message_option(&self) -> Option<MessageOption> {
+        match self {
+            TodoCommand::Fixup(message, _) => *message,
+            _ => None,
+        }
+    }
+
+    pub fn set_message_option(&mut self, option: Option<MessageOption>) {
+        if let TodoCommand::Fixup(message, _) = self {
+            *message = option;
+        }
+    }
+
+    fn format(&self, abbreviate: bool) -> String {
+        let mut str = String::from(if abbreviate {
+            self.abbreviation()
+        } else {
+            self.name()
+        });
+        if let Some(message) = self.message_option() {
+            str.push(' ');
+            str.push_str(message.flag());
+        }
+        let parameters = self.parameters();
+        if !parameters.is_empty() {
+            str.push(' ');
+            str.push_str(&parameters);
+        }
+        str
+    }
+}
+
+/// Splits the ` # subject` that git writes after the label of `reset` and `merge` lines.
+fn split_subject(parameters: &str) -> (&str, Option<String>) {
+    match parameters.split_once(" # ") {
+        Some((label, subject)) => (label, Some(subject.to_string())),
+        None => (parameters, None),
+    }
+}
+
+fn with_subject(label: &str, subject: &Option<String>) -> String {
+    match subject {
+        Some(subject) => format!("{label} # {subject}"),
+        None => label.to_string(),
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct SourceLine {
+    line: String,
+    command: TodoCommand,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TodoItem {
+    pub command: TodoCommand,
+    source: Option<SourceLine>,
+    /// The command this item had before being turned into a `drop`.
+    dropped: Option<Box<TodoCommand>>,
+}
+
+impl TodoItem {
+    pub fn new(command: TodoCommand) -> Self {
+        TodoItem {
+            command,
+            source: None,
+            dropped: None,
+        }
+    }
+
+    fn parse(line: &str) -> Self {
+        let trimmed = line.trim();
+        let (command, parameters) = trimmed.split_once(' ').unwrap_or((trimmed, ""));
+        let command = TodoCommand::parse(command, parameters);
+        TodoItem {
+            command: command.clone(),
+            source: Some(SourceLine {
+                line: line.to_string(),
+                command,
+            }),
+            dropped: None,
+        }
+    }
+
+    pub fn is_dropped(&self) -> bool {
+        matches!(self.command, TodoCommand::Drop(_))
+    }
+
+    /// Turns the item into a `drop`, or a dropped item back into its previous command
+    /// (`pick` if it was dropped from the start). Returns `false` if the item has no
+    /// commit to drop.
+    pub fn toggle_drop(&mut self) -> bool {
+        let Some(commit) = self.command.commit().cloned() else {
+            return false;
+        };
+        if self.is_dropped() {
+            self.command = self
+                .dropped
+                .take()
+                .map_or(TodoCommand::Pick(commit), |command| *command);
+        } else {
+            let command = std::mem::replace(&mut self.command, TodoCommand::Drop(commit));
+            self.dropped = Some(Box::new(command));
+        }
+        true
+    }
+
+    fn format(&self, abbreviate: bool) -> String {
+        match &self.source {
+            Some(source) if source.command == self.command => source.line.clone(),
+            _ => self.command.format(abbreviate),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TodoLine {
+    Item(TodoItem),
+    /// A comment or blank line. It is not editable and is written back verbatim.
+    Comment(String),
+}
+
+impl TodoLine {
+    pub fn item(&self) -> Option<&TodoItem> {
+        match self {
+            TodoLine::Item(item) => Some(item),
+            TodoLine::Comment(_) => None,
+        }
+    }
+
+    fn format(&self, abbreviate: bool) -> String {
+        match self {
+            TodoLine::Item(item) => item.format(abbreviate),
+            TodoLine::Comment(line) => line.clone(),
+        }
+    }
+}
+
+/// The characters git may choose from when `core.commentChar` is `auto`.
+const COMMENT_CHAR_CANDIDATES: &str = "#;@!$%^&|:";
+
+/// Guesses the comment character from the lines of a todo list, which always include
+/// git's help text as comments. Falls back to `#`.
+pub fn detect_comment_char(todo_list_lines: &[&str]) -> char {
+    todo_list_lines
+        .iter()
+        .filter_map(|line| line.trim_start().chars().next())
+        .find(|char| COMMENT_CHAR_CANDIDATES.contains(*char))
+        .unwrap_or('#')
+}
+
+pub fn parse_todo_list(todo_list_lines: &[&str], comment_char: char) -> Vec<TodoLine> {
+    todo_list_lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with(comment_char) {
+                TodoLine::Comment(line.to_string())
+            } else {
+                TodoLine::Item(TodoItem::parse(line))
+            }
+        })
+        .collect()
+}
+
+pub fn format_todo_list(todo_lines: &[TodoLine], abbreviate: bool) -> Result<String> {
+    let mut str = String::new();
+    for line in todo_lines {
+        writeln!(&mut str, "{}", line.format(abbreviate))?;
+    }
+    Ok(str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        detect_comment_char, format_todo_list, parse_todo_list, CommitLine, MessageOption,
+        TodoCommand, TodoLine,
+    };
+
+    fn commit(hash: &str, subject: &str) -> CommitLine {
+        CommitLine {
+            hash: hash.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_argument_shapes() {
+        let lines = [
+            "pick 1a2b3c Add feature",
+            "f -C 4d5e6f fixup! Add feature",
+            "x cargo test --all",
+            "b",
+            "label onto",
+            "reset onto # Add feature",
+            "merge -c 7a8b9c topic # Merge branch 'topic'",
+            "merge other",
+            "update-ref refs/heads/topic",
+            "frobnicate 1a2b3c",
+        ];
+
+        let commands: Vec<_> = parse_todo_list(&lines, '#')
+            .into_iter()
+            .map(|line| line.item().unwrap().command.clone())
+            .collect();
+
+        assert_eq!(
+            commands,
+            [
+                TodoCommand::Pick(commit("1a2b3c", "Add feature")),
+                TodoCommand::Fixup(
+                    Some(MessageOption::Use),
+                    commit("4d5e6f", "fixup! Add feature")
+                ),
+                TodoCommand::Exec("cargo test --all".to_string()),
+                TodoCommand::Break,
+                TodoCommand::Label("onto".to_string()),
+                TodoCommand::Reset {
+                    label: "onto".to_string(),
+                    subject: Some("Add feature".to_string()),
+                },
+                TodoCommand::Merge {
+                    commit: Some((MessageOption::Edit, "7a8b9c".to_string())),
+                    label: "topic".to_string(),
+                    subject: Some("Merge branch 'topic'".to_string()),
+                },
+                TodoCommand::Merge {
+                    commit: None,
+                    label: "other".to_string(),
+                    subject: None,
+                },
+                TodoCommand::UpdateRef("refs/heads/topic".to_string()),
+                TodoCommand::Unknown {
+                    command: "frobnicate".to_string(),
+                    parameters: "1a2b3c".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_lines_round_trip() {
+        let lines = [
+            "pick 1a2b3c  Subject with  extra spaces ",
+            "  s 4d5e6f Indented abbreviation",
+            "merge -C 7a8b9c topic # Merge branch 'topic'",
+            "",
+            "break",
+            "# Rebase 1a2b3c..7a8b9c onto 0f0f0f (4 commands)",
+            "#",
+        ];
+
+        let items = parse_todo_list(&lines, '#');
+
+        assert_eq!(
+            format_todo_list(&items, false).unwrap(),
+            lines.join("\n") + "\n"
+        );
+    }
+
+    #[test]
+    fn changed_lines_are_reformatted() {
+        let lines = ["  p 1a2b3c Subject", "x make", "pick 4d5e6f Other"];
+        let mut items = parse_todo_list(&lines, '#');
+        if let TodoLine::Item(item) = &mut items[0] {
+            item.command = TodoCommand::parse("fixup", &item.command.parameters());
+            item.command.set_message_option(Some(MessageOption::Edit));
+        }
+        if let TodoLine::Item(item) = &mut items[1] {
+            item.command = TodoCommand::parse("exec", "make test");
+        }
+
+        assert_eq!(
+            format_todo_list(&items, false).unwrap(),
+            "fixup -c 1a2b3c Subject\nexec make test\npick 4d5e6f Other\n"
+        );
+        assert_eq!(
+            format_todo_list(&items, true).unwrap(),
+            "f -c 1a2b3c Subject\nx make test\npick 4d5e6f Other\n"
+        );
+    }
+
+    #[test]
+    fn toggle_drop() {
+        let lines = ["  f -C 1a2b3c Subject", "drop 4d5e6f Other", "x make"];
+        let mut items = parse_todo_list(&lines, '#');
+        for line in &mut items {
+            if let TodoLine::Item(item) = line {
+                item.toggle_drop();
+            }
+        }
+
+        assert_eq!(
+            format_todo_list(&items, false).unwrap(),
+            "drop 1a2b3c Subject\npick 4d5e6f Other\nx make\n"
+        );
+
+        if let TodoLine::Item(item) = &mut items[0] {
+            assert!(item.toggle_drop());
+        }
+        if let TodoLine::Item(item) = &mut items[2] {
+            assert!(!item.toggle_drop());
+        }
+        assert_eq!(
+            format_todo_list(&items, false).unwrap(),
+            "  f -C 1a2b3c Subject\npick 4d5e6f Other\nx make\n"
+        );
+    }
+
+    #[test]
+    fn comment_char() {
+        let lines = [
+            "; custom comment",
+            "pick 1a2b3c Subject",
+            "# not a comment",
+            ";",
+        ];
+
+        assert_eq!(detect_comment_char(&lines), ';');
+        assert_eq!(detect_comment_char(&["pick 1a2b3c Subject"]), '#');
+
+        let items = parse_todo_list(&lines, ';');
+        assert!(matches!(items[0], TodoLine::Comment(_)));
+        assert!(matches!(items[2], TodoLine::Item(_)));
+        assert!(matches!(items[3], TodoLine::Comment(_)));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// The todo file as git wrote it, so that its encoding and line endings are kept when the
+/// new todo list is written.
+pub struct TodoFile {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    text: String,
+    line_endings: LineEndings,
+    encoding: Encoding,
+}
+
+impl TodoFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("Cannot read the todo list from {}", path.display()))?;
+        let (text, encoding) = Encoding::decode(&bytes);
+        Ok(TodoFile {
+            path: path.to_path_buf(),
+            line_endings: LineEndings::detect(&text),
+            bytes,
+            text,
+            encoding,
+        })
+    }
+
+    /// The content, with invalid UTF-8 sequences replaced.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Writes the todo list, with `\n` line endings, in place of the content.
+    pub fn write(&self, todo_list: &str) -> Result<()> {
+        let content = self.encoding.encode(&self.line_endings.apply(todo_list));
+        write(&self.path, &content, &self.bytes)
+    }
+}
+
+/// The lines that are not valid UTF-8, like commit subjects in legacy encodings.
+struct Encoding {
+    /// The end of each line, from the commit hash or the first invalid sequence, as decoded
+    /// and as read.
+    invalid_tails: Vec<(String, Vec<u8>)>,
+}
+
+impl Encoding {
+    fn decode(bytes: &[u8]) -> (String, Self) {
+        let mut invalid_tails = Vec::new();
+        for line in bytes.split(|byte| *byte == b'\n') {
+            if let Err(error) = std::str::from_utf8(line) {
+                let tail = &line[tail_start(line).min(error.valid_up_to())..];
+                invalid_tails.push((String::from_utf8_lossy(tail).into_owned(), tail.to_vec()));
+            }
+        }
+        (
+            String::from_utf8_lossy(bytes).into_owned(),
+            Encoding { invalid_tails },
+        )
+    }
+
+    /// Restores the bytes of the invalid lines, wherever their decoded text was kept. Lines
+    /// decoded the same way take the bytes of the read lines in order, as they are told apart
+    /// by their position only.
+    fn encode(&self, text: &str) -> Vec<u8> {
+        let mut used = vec![false; self.invalid_tails.len()];
+        let mut bytes = Vec::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let (line, newline) = match line.strip_suffix('\n') {
+                Some(line) => (line, "\n"),
+                None => (line, ""),
+            };
+            // The first unused of the longest tails, or a used one for a duplicated line
+            let tail = self
+                .invalid_tails
+                .iter()
+                .enumerate()
+                .filter(|(_, (decoded, _))| line.ends_with(decoded.as_str()))
+                .min_by_key(|(index, (decoded, _))| (Reverse(decoded.len()), used[*index]));
+            match tail {
+                Some((index, (decoded, raw))) => {
+                    used[index] = true;
+                    bytes.extend(&line.as_bytes()[..line.len() - decoded.len()]);
+                    bytes.extend(raw);
+                }
+                None => bytes.extend(line.as_bytes()),
+            }
+            bytes.extend(newline.as_bytes());
+        }
+        bytes
+    }
+}
+
+/// Where the commit hash starts, after the command and its options, as the command may
+/// change while the rest of the line is kept.
+fn tail_start(line: &[u8]) -> usize {
+    let mut start = 0;
+    for (index, word) in line.split(|byte| *byte == b' ').enumerate() {
+        if index > 0 && !word.starts_with(b"-") {
+            return start;
+        }
+        start += word.len() + 1;
+    }
+    line.len()
+}
+
+/// The line endings of the todo file, kept when writing it back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct LineEndings {
+    crlf: bool,
+    final_newline: bool,
+}
+
+impl LineEndings {
+    /// Follows the first line, as git writes all of them the same way.
+    fn detect(text: &str) -> Self {
+        LineEndings {
+            crlf: text
+                .find('\n')
+                .is_some_and(|index| text[..index].ends_with('\r')),
+            final_newline: text.is_empty() || text.ends_with('\n'),
+        }
+    }
+
+    /// Converts a text with `\n` line endings to these ones.
+    fn apply(&self, text: &str) -> String {
+        let text = if self.final_newline {
+            text
+        } else {
+            text.strip_suffix('\n').unwrap_or(text)
+        };
+        if self.crlf {
+            text.replace('\n', "\r\n")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Where the content of the todo file is kept before it is replaced.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".orig");
+    path.with_file_name(name)
+}
+
+/// Replaces the content of the file, keeping the previous one as a backup. The new content is
+/// written to a temporary file first, then renamed over the file, so that git never reads a
+/// partly written list.
+fn write(path: &Path, content: &[u8], previous: &[u8]) -> Result<()> {
+    let backup = backup_path(path);
+    fs::write(&backup, previous)
+        .with_context(|| format!("Cannot back up the todo list to {}", backup.display()))?;
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    let result = write_synced(&temp, content).and_then(|_| {
+        fs::rename(&temp, path)?;
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.with_context(|| format!("Cannot write the todo list to {}", path.display()))
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{backup_path, Encoding, LineEndings, TodoFile};
+
+    #[test]
+    fn line_endings() {
+        let lf = "pick 1a1a1a A\npick 2b2b2b B\n";
+
+        let crlf = LineEndings::detect("pick 1a1a1a A\r\n# comment\r\n");
+        assert_eq!(crlf.apply(lf), "pick 1a1a1a A\r\npick 2b2b2b B\r\n");
+
+        let no_final_newline = LineEndings::detect("pick 1a1a1a A\npick 2b2b2b B");
+        assert_eq!(no_final_newline.apply(lf), "pick 1a1a1a A\npick 2b2b2b B");
+
+        let both = LineEndings::detect("pick 1a1a1a A\r\npick 2b2b2b B");
+        assert_eq!(both.apply(lf), "pick 1a1a1a A\r\npick 2b2b2b B");
+
+        assert_eq!(LineEndings::detect("").apply(lf), lf);
+        assert_eq!(LineEndings::detect("noop").apply(""), "");
+    }
+
+    #[test]
+    fn invalid_utf8_round_trip() {
+        let bytes = b"pick 1a1a1a Caf\xe9\npick 2b2b2b B\n# \xff\n";
+        let (text, encoding) = Encoding::decode(bytes);
+        assert_eq!(text, "pick 1a1a1a Caf\u{FFFD}\npick 2b2b2b B\n# \u{FFFD}\n");
+        assert_eq!(encoding.encode(&text), bytes);
+
+        let changed = "pick 2b2b2b B\nfixup -C 1a1a1a Caf\u{FFFD}\n# \u{FFFD}\n";
+        assert_eq!(
+            encoding.encode(changed),
+            b"pick 2b2b2b B\nfixup -C 1a1a1a Caf\xe9\n# \xff\n"
+        );
+
+        // Decoded the same way
+        let bytes = b"pick 1a1a1a A\n# caf\xe9\n# caf\xe8\n";
+        let (text, encoding) = Encoding::decode(bytes);
+        assert_eq!(encoding.encode(&text), bytes);
+        assert_eq!(
+            encoding.encode(&text.replace("pick", "drop")),
+            b"drop 1a1a1a A\n# caf\xe9\n# caf\xe8\n"
+        );
+    }
+
+    #[test]
+    fn write_with_backup() {
+        let dir = env::temp_dir().join(format!("gire-todo-file-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("git-rebase-todo");
+        fs::write(&path, "pick 1a1a1a A\n").unwrap();
+
+        TodoFile::read(&path)
+            .unwrap()
+            .write("drop 1a1a1a A\n")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "drop 1a1a1a A\n");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "pick 1a1a1a A\n"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,126 @@
+use std::collections::{HashMap, HashSet};
+
 use ratatui::{
     layout::{Constraint, Direction, Layout, Margin, Rect},
-    style::{Style, Stylize},
+    style::{Color, Style, Stylize},
     text::{Line, Span},
     widgets::{
         Block, Borders, Clear, ListItem, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
-        ScrollbarState,
+        ScrollbarState, Wrap,
     },
     Frame,
 };
 
-use crate::app::{App, Command, EditingWhat, Mode, RebaseConfirmation, TodoItem, COMMANDS};
+use crate::{
+    app::{App, Command, EditingWhat, Mode, RebaseConfirmation, COMMANDS},
+    config::Keymap,
+    diff::Diff,
+    fixup_tree::fixup_groups,
+    git::CommitDetails,
+    keys::{Action, Keys},
+    split::{group_count, SplitFile, SplitPlan},
+    todo::{TodoCommand, TodoLine},
+    validation::Problem,
+};
 
-impl<'a> From<TodoItem> for ListItem<'a> {
-    fn from(item: TodoItem) -> Self {
-        ListItem::new(format!("{:10} {}", item.command, item.parameters))
+impl<'a> From<TodoLine> for ListItem<'a> {
+    fn from(line: TodoLine) -> Self {
+        todo_line_list_item(&line, None, &HashMap::new(), &HashMap::new())
     }
 }
 
+/// Where a line is in a group of lines folded into a commit.
+#[derive(Clone, Copy)]
+enum Branch {
+    /// The commit, with the number of lines hidden if the group is folded.
+    Parent {
+        hidden: Option<usize>,
+    },
+    Child {
+        last: bool,
+    },
+}
+
+fn todo_line_list_item<'a>(
+    line: &TodoLine,
+    branch: Option<Branch>,
+    reorder_conflicts: &HashMap<String, Vec<String>>,
+    split_plans: &HashMap<String, SplitPlan>,
+) -> ListItem<'a> {
+    match line {
+        TodoLine::Item(item) => {
+            let glyph = match branch {
+                Some(Branch::Child { last: false }) => "├─ ",
+                Some(Branch::Child { last: true }) => "└─ ",
+                _ => "",
+            };
+            let text = todo_command_text(&item.command);
+            let conflicts = item
+                .command
+                .commit()
+                .and_then(|commit| reorder_conflicts.get(&commit.hash));
+            let split_plan = match &item.command {
+                TodoCommand::Edit(commit) => split_plans.get(&commit.hash),
+                _ => None,
+            };
+            let text = if item.is_dropped() {
+                Span::from(text).style(Style::default().dim().crossed_out())
+            } else {
+                Span::from(text)
+            };
+            let mut spans = vec![Span::from(glyph).dim(), text];
+            if let Some(Branch::Parent {
+                hidden: Some(hidden),
+            }) = branch
+            {
+                spans.push(
+                    Span::from(format!("  ▸ {hidden} folded"))
+                        .style(Style::default().fg(Color::Blue)),
+                );
+            }
+            if let Some(plan) = split_plan {
+                spans.push(
+                    Span::from(format!("  ✂ split into {} commits", group_count(plan)))
+                        .style(Style::default().fg(Color::Magenta)),
+                );
+            }
+            if let Some(conflicts) = conflicts {
+                spans.push(
+                    Span::from(format!("  ⚠ may conflict with {}", conflicts.join(", ")))
+                        .style(Style::default().fg(Color::Yellow)),
+                );
+            }
+            ListItem::new(Line::from(spans))
+        }
+        TodoLine::Comment(line) => ListItem::new(line.clone()).style(Style::default().dim()),
+    }
+}
+
+/// The command, with its option, and its parameters, aligned.
+fn todo_command_text(command: &TodoCommand) -> String {
+    format!("{:10} {}", command.label(), command.parameters())
+}
+
 impl<'a> From<&Command> for ListItem<'a> {
     fn from(command: &Command) -> Self {
-        ListItem::new(command.0)
+        ListItem::new(command.label())
+    }
+}
+
+impl<'a> From<SplitFile> for ListItem<'a> {
+    fn from(file: SplitFile) -> Self {
+        ListItem::new(format!("{}  {}", file.group, file.path))
+    }
+}
+
+impl<'a> From<Problem> for ListItem<'a> {
+    fn from(problem: Problem) -> Self {
+        let warning = if problem.warning { "warning: " } else { "" };
+        ListItem::new(format!(
+            "line {}: {warning}{}",
+            problem.index + 1,
+            problem.message
+        ))
     }
 }
 
@@ -49,17 +150,80 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
         [chunks[0], chunks[1]]
     };
 
-    let todo_items_count = app.todo_list.items().len();
-    let (todo_list, todo_list_state) = app.todo_list.widget_and_state();
+    let todo_list_area = if app.show_commit_details {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(todo_list_area);
+
+        let details = commit_details_paragraph(app.selected_commit_details());
+        frame.render_widget(details, chunks[1]);
+
+        chunks[0]
+    } else {
+        todo_list_area
+    };
+
+    let todo_items_count = app.todo_list.shown_len();
+    let reorder_conflicts = &app.reorder_conflicts;
+    let split_plans = &app.split_plans;
+    let mut branches = HashMap::new();
+    for group in fixup_groups(app.todo_list.items()) {
+        let folded = app.is_folded(group.start);
+        let hidden = folded.then_some(group.len() - 1);
+        branches.insert(group.start, Branch::Parent { hidden });
+        for index in group.start + 1..group.end {
+            let last = index + 1 == group.end;
+            branches.insert(index, Branch::Child { last });
+        }
+    }
+    let multi_selection: HashSet<_> = if app.todo_list.has_multi_selection() {
+        app.todo_list.multi_selection().into_iter().collect()
+    } else {
+        HashSet::new()
+    };
+    // Search matches are highlighted, unless they are the only lines shown
+    let matches: HashSet<_> = match &app.search {
+        Some(search) if !search.filter => app.matching_indices().into_iter().collect(),
+        _ => HashSet::new(),
+    };
+    let title = {
+        let mut title = " Git Interactive Rebase ".to_string();
+        if app.drop_on_remove {
+            title.push_str("(DELETE drops) ");
+        }
+        if let Some(search) = &app.search {
+            let kind = if search.filter { "filter" } else { "search" };
+            title.push_str(&format!("({kind}: {}) ", search.query));
+        }
+        title
+    };
+    let (todo_list, todo_list_state) = app.todo_list.widget_and_state_with(|index, line| {
+        let branch = branches.get(&index).copied();
+        let list_item = todo_line_list_item(line, branch, reorder_conflicts, split_plans);
+        let mut style = Style::default();
+        if matches.contains(&index) {
+            style = style.fg(Color::Cyan).bold();
+        }
+        if multi_selection.contains(&index) {
+            style = style.bg(Color::DarkGray);
+        }
+        if style == Style::default() {
+            list_item
+        } else {
+            list_item.style(style)
+        }
+    });
     let todo_list = todo_list
         .highlight_style(Style::default().reversed())
         .block(
             Block::default()
-                .title(" Git Interactive Rebase ")
+                .title(title)
                 .borders(Borders::ALL)
                 .padding(Padding::horizontal(1)),
         );
     app.page_length = todo_list_area.height as usize - 2;
+    app.screen_areas.todo_list = todo_list_area.inner(&Margin::new(2, 1));
     frame.render_stateful_widget(todo_list, todo_list_area, todo_list_state);
 
     let scrollbar_area = todo_list_area.inner(&Margin {
@@ -75,25 +239,108 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
         frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
     }
 
-    let footer_content: &[(&[&'static str], &'static str)] = match &mut app.mode {
+    let keys = &app.config.keys;
+    let footer_content: Vec<(Vec<String>, &'static str)> = match &mut app.mode {
         Mode::Main => {
-            if app.todo_list.items().is_empty() {
-                &[
-                    (&["INSERT"], "insert"),
-                    (&["o"], "show original todo list"),
-                    (&["ESC", "Q"], "quit"),
-                ]
+            let mut hints = if app.todo_list.has_multi_selection() {
+                action_hints(
+                    keys,
+                    &[
+                        (&[Action::MoveUp, Action::MoveDown], "move selected"),
+                        (&[Action::Edit], "change command"),
+                        (&[Action::Remove], "remove selected"),
+                        (&[Action::ToggleDropOnRemove], "toggle drop on delete"),
+                        (&[Action::Duplicate], "duplicate selected"),
+                        (&[Action::InsertExec], "exec after selected"),
+                        (&[Action::Mark], "mark"),
+                        (&[Action::ExtendUp, Action::ExtendDown], "extend"),
+                        (&[Action::Undo, Action::Redo], "undo/redo"),
+                        (&[Action::ClearSelection], "clear selection"),
+                    ],
+                )
+            } else if app.todo_list.items().is_empty() {
+                action_hints(
+                    keys,
+                    &[
+                        (&[Action::Insert], "insert"),
+                        (&[Action::Undo, Action::Redo], "undo/redo"),
+                        (&[Action::ShowOriginal], "show original todo list"),
+                        (&[Action::Search, Action::Filter], "search/filter"),
+                        (&[Action::EditAsText], "edit as text"),
+                        (&[Action::Quit], "quit"),
+                    ],
+                )
+            } else if app.config.keymap == Keymap::Vim {
+                let mut hints = fixed_hints(&[
+                    (&["j", "k"], "select"),
+                    (&["J", "K"], "move"),
+                    (&["p", "r", "e", "s", "f", "dd"], "set command"),
+                ]);
+                hints.extend(action_hints(keys, &[(&[Action::Edit], "edit")]));
+                hints.extend(fixed_hints(&[
+                    (&["yy", "P"], "yank/paste"),
+                    (&["gg", "G"], "first/last"),
+                    (&["V"], "select range"),
+                    (&["."], "repeat"),
+                ]));
+                hints.extend(action_hints(
+                    keys,
+                    &[
+                        (&[Action::Mark], "mark"),
+                        (&[Action::Undo, Action::Redo], "undo/redo"),
+                        (&[Action::ViewDiff], "view diff"),
+                        (&[Action::Search, Action::Filter], "search/filter"),
+                        (&[Action::Autosquash], "autosquash"),
+                        (&[Action::InsertExec], "exec after each"),
+                        (&[Action::EditMessage], "edit message"),
+                        (&[Action::Split], "split"),
+                        (&[Action::ToggleFold], "fold/unfold fixups"),
+                        (&[Action::EditAsText], "edit as text"),
+                        (&[Action::Quit], "quit"),
+                    ],
+                ));
+                hints
             } else {
-                &[
-                    (&["CTRL+↑", "CTRL+↓"], "move"),
-                    (&["ENTER"], "edit"),
-                    (&["INSERT"], "insert"),
-                    (&["DELETE"], "remove"),
-                    (&["2"], "duplicate"),
-                    (&["o"], "show original todo list"),
-                    (&["ESC", "Q"], "quit"),
-                ]
+                action_hints(
+                    keys,
+                    &[
+                        (&[Action::MoveUp, Action::MoveDown], "move"),
+                        (&[Action::Edit], "edit"),
+                        (&[Action::Insert], "insert"),
+                        (&[Action::Remove], "remove"),
+                        (&[Action::ToggleDropOnRemove], "toggle drop on delete"),
+                        (&[Action::Duplicate], "duplicate"),
+                        (&[Action::Mark], "mark"),
+                        (
+                            &[Action::ExtendUp, Action::ExtendDown, Action::SelectRange],
+                            "select range",
+                        ),
+                        (&[Action::Undo, Action::Redo], "undo/redo"),
+                        (&[Action::ShowOriginal], "show original todo list"),
+                        (&[Action::ViewDiff], "view diff"),
+                        (&[Action::Search, Action::Filter], "search/filter"),
+                        (&[Action::Autosquash], "autosquash"),
+                        (&[Action::InsertExec], "exec after each"),
+                        (&[Action::EditMessage], "edit message"),
+                        (&[Action::Split], "split"),
+                        (&[Action::ToggleFold], "fold/unfold fixups"),
+                        (&[Action::EditAsText], "edit as text"),
+                        (&[Action::ToggleDetails], "toggle commit details"),
+                        (&[Action::Quit], "quit"),
+                    ],
+                )
+            };
+            if app.search.is_some() {
+                let match_hints = action_hints(
+                    keys,
+                    &[(
+                        &[Action::NextMatch, Action::PreviousMatch],
+                        "next/previous match",
+                    )],
+                );
+                hints.splice(0..0, match_hints);
             }
+            hints
         }
 
         Mode::Editing {
@@ -120,12 +367,13 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
 
             frame.render_widget(Clear, cmds_area);
             frame.render_stateful_widget(cmds, cmds_area, cmds_state);
+            app.screen_areas.commands = cmds_area.inner(&Margin::new(2, 1));
 
-            &[
+            fixed_hints(&[
                 (&["TAB"], "edit parameters"),
                 (&["ENTER"], "confirm"),
                 (&["ESC"], "cancel editing"),
-            ]
+            ])
         }
 
         Mode::Editing {
@@ -144,77 +392,299 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
             let widget = widget.block(Block::default().borders(Borders::ALL));
             frame.render_stateful_widget(widget, params_area, widget_state);
 
-            &[
+            fixed_hints(&[
                 (&["TAB"], "edit command"),
                 (&["ENTER"], "confirm"),
                 (&["ESC"], "cancel editing"),
-            ]
+            ])
         }
 
-        Mode::ShowingOriginal { scroll } => {
-            let original_todo_area = {
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(5),
-                        Constraint::Min(1),
-                        Constraint::Length(5),
-                    ])
-                    .split(frame.size());
+        Mode::Searching { input, filter, .. } => {
+            let search_area = Rect {
+                y: todo_list_area.bottom().saturating_sub(3),
+                height: 3.min(todo_list_area.height),
+                ..todo_list_area
+            };
+            let (widget, widget_state) = input.widget_and_state();
+            let widget = widget.block(
+                Block::default()
+                    .title(if *filter { " Filter " } else { " Search " })
+                    .borders(Borders::ALL),
+            );
+            frame.render_widget(Clear, search_area);
+            frame.render_stateful_widget(widget, search_area, widget_state);
 
-                Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Length(7),
-                        Constraint::Min(1),
-                        Constraint::Length(7),
-                    ])
-                    .split(chunks[1])[1]
+            fixed_hints(&[(&["ENTER"], "confirm"), (&["ESC"], "cancel")])
+        }
+
+        Mode::EditingMessage { input, index } => {
+            let commit = app.todo_list.items()[*index]
+                .item()
+                .and_then(|item| item.command.commit());
+            let title = format!(
+                " New message of {} ",
+                commit.map_or("", |commit| commit.hash.as_str())
+            );
+
+            let message_area = popup_area(frame);
+            let (widget, widget_state) = input.widget_and_state();
+            let widget = widget.block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .padding(Padding::horizontal(1)),
+            );
+            frame.render_widget(Clear, message_area);
+            frame.render_stateful_widget(widget, message_area, widget_state);
+
+            fixed_hints(&[
+                (&["CTRL+S"], "save (empty to let git ask)"),
+                (&["ESC"], "cancel"),
+            ])
+        }
+
+        Mode::PlanningSplit { index, files } => {
+            let commit = app.todo_list.items()[*index]
+                .item()
+                .and_then(|item| item.command.commit());
+            let title = format!(
+                " Split {} into commits ",
+                commit.map_or("", |commit| commit.hash.as_str())
+            );
+
+            let split_area = popup_area(frame);
+            let (files, files_state) = files.widget_and_state();
+            let files = files.highlight_style(Style::default().reversed()).block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .padding(Padding::horizontal(1)),
+            );
+            frame.render_widget(Clear, split_area);
+            frame.render_stateful_widget(files, split_area, files_state);
+
+            fixed_hints(&[
+                (&["1-9"], "put the file in that commit"),
+                (&["ENTER"], "mark for splitting"),
+                (&["ESC"], "cancel"),
+            ])
+        }
+
+        Mode::InsertingExec {
+            input,
+            selected_only,
+        } => {
+            let exec_area = Rect {
+                y: todo_list_area.bottom().saturating_sub(3),
+                height: 3.min(todo_list_area.height),
+                ..todo_list_area
             };
+            let (widget, widget_state) = input.widget_and_state();
+            let widget = widget.block(
+                Block::default()
+                    .title(if *selected_only {
+                        " exec after the selected commits "
+                    } else {
+                        " exec after each commit "
+                    })
+                    .borders(Borders::ALL),
+            );
+            frame.render_widget(Clear, exec_area);
+            frame.render_stateful_widget(widget, exec_area, widget_state);
 
-            let max_scroll = (app.original_todo_list_lines.len() as u16)
-                .saturating_sub(original_todo_area.height - 2 /*margins*/);
-            *scroll = std::cmp::min(*scroll, max_scroll);
+            fixed_hints(&[(&["ENTER"], "insert"), (&["ESC"], "cancel")])
+        }
 
-            let p = Paragraph::new(
-                app.original_todo_list_lines
-                    .iter()
-                    .map(|line| Line::from(*line))
-                    .collect::<Vec<_>>(),
+        Mode::ShowingOriginal { scroll } => {
+            let lines = app
+                .original_todo_list_lines
+                .iter()
+                .map(|line| Line::from(*line))
+                .collect();
+            render_scrollable_popup(frame, "Original todo list".to_string(), lines, scroll);
+
+            fixed_hints(&[(&["ESC"], "dismiss")])
+        }
+
+        Mode::PreviewingAutosquash {
+            todo_lines: None,
+            scroll,
+        } => {
+            let lines = vec![Line::from(
+                "There is no fixup!, squash! or amend! commit to move after its target.",
+            )];
+            render_scrollable_popup(frame, "Autosquash".to_string(), lines, scroll);
+
+            fixed_hints(&[(&["ESC"], "dismiss")])
+        }
+
+        Mode::PreviewingAutosquash {
+            todo_lines: Some(todo_lines),
+            scroll,
+        } => {
+            let current = app.todo_list.items();
+            let lines = todo_lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    let (text, style) = match line {
+                        TodoLine::Item(item) => {
+                            (todo_command_text(&item.command), Style::default())
+                        }
+                        TodoLine::Comment(line) => (line.clone(), Style::default().dim()),
+                    };
+                    // Highlighting the lines that the arrangement changes
+                    let style = if current.get(index) == Some(line) {
+                        style
+                    } else {
+                        style.fg(Color::Yellow).bold()
+                    };
+                    Line::from(Span::from(text).style(style))
+                })
+                .collect();
+            render_scrollable_popup(frame, "Autosquash preview".to_string(), lines, scroll);
+
+            fixed_hints(&[(&["ENTER"], "apply"), (&["ESC"], "cancel")])
+        }
+
+        Mode::RestoringSession { todo_lines, scroll } => {
+            let lines = todo_lines
+                .iter()
+                .map(|line| match line {
+                    TodoLine::Item(item) => Line::from(todo_command_text(&item.command)),
+                    TodoLine::Comment(line) => Line::from(Span::from(line.clone()).dim()),
+                })
+                .collect();
+            let title = "Restore the list of the interrupted session?".to_string();
+            render_scrollable_popup(frame, title, lines, scroll);
+
+            fixed_hints(&[(&["Y"], "restore"), (&["N"], "start over")])
+        }
+
+        Mode::ShowingDiff { diff, scroll } => {
+            let title = format!(
+                "Diff of {} ({})",
+                diff.commit,
+                if diff.word_diff { "word" } else { "unified" }
+            );
+            render_scrollable_popup(frame, title, diff_lines(diff), scroll);
+
+            action_hints(
+                keys,
+                &[
+                    (
+                        &[Action::DiffNextFile, Action::DiffPreviousFile],
+                        "next/previous file",
+                    ),
+                    (
+                        &[Action::DiffNextHunk, Action::DiffPreviousHunk],
+                        "next/previous hunk",
+                    ),
+                    (&[Action::DiffToggleWordDiff], "toggle word diff"),
+                    (&[Action::DiffClose], "dismiss"),
+                ],
             )
-            .scroll((*scroll, 0))
-            .block(
+        }
+
+        Mode::ShowingProblems {
+            problems,
+            can_proceed,
+        } => {
+            let height = (problems.items().len() as u16 + 2)
+                .min(todo_list_area.height / 2)
+                .max(3)
+                .min(footer_area.y);
+            let problems_area = Rect {
+                x: 0,
+                y: footer_area.y - height,
+                width: frame.size().width,
+                height,
+            };
+
+            let (title, color) = if *can_proceed {
+                (" The rebase may fail on these lines ", Color::Yellow)
+            } else {
+                (" The rebase would fail on these problems ", Color::Red)
+            };
+            let (problems, problems_state) = problems.widget_and_state();
+            let problems = problems.highlight_style(Style::default().reversed()).block(
                 Block::default()
-                    .title("Original todo list")
+                    .title(title)
+                    .title_style(Style::default().fg(color).bold())
                     .borders(Borders::ALL)
                     .padding(Padding::horizontal(1)),
             );
 
-            frame.render_widget(Clear, original_todo_area);
-            frame.render_widget(p, original_todo_area);
+            frame.render_widget(Clear, problems_area);
+            frame.render_stateful_widget(problems, problems_area, problems_state);
 
-            let scrollbar_area = original_todo_area.inner(&Margin {
-                horizontal: 0,
-                vertical: 1,
-            });
-            if let Some(mut scrollbar_state) = scrollbar_state_from_offset(
-                app.original_todo_list_lines.len(),
-                scrollbar_area.height as usize,
-                *scroll as usize,
-            ) {
-                let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
-                frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
+            if *can_proceed {
+                fixed_hints(&[
+                    (&["↑", "↓"], "go to problem"),
+                    (&["ENTER"], "proceed"),
+                    (&["ESC"], "fix"),
+                    (&["N"], "quit and don't rebase"),
+                ])
+            } else {
+                fixed_hints(&[
+                    (&["↑", "↓"], "go to problem"),
+                    (&["ENTER", "ESC"], "fix"),
+                    (&["N"], "quit and don't rebase"),
+                ])
             }
-
-            &[(&["ESC"], "dismiss")]
         }
 
-        Mode::Quitting(rebase_confirmation) => {
+        Mode::Quitting {
+            confirmation,
+            missing_commits,
+        } => {
             const PADDING: u16 = 2;
-            let question = "Proceed to rebase?";
+            let blocked = !confirmation.items().contains(&RebaseConfirmation(true));
+
+            let max_height = frame.size().height.saturating_sub(2);
+            // The commits are listed in the room left by the other lines of the dialog
+            let other_lines = 5/*borders and spacings*/ + 2/*heading and blank line*/
+                + if blocked { 2 } else { 1 }
+                + confirmation.items().len();
+            let room = (max_height as usize).saturating_sub(other_lines).max(1);
+            let shown_commits = if missing_commits.len() > room {
+                room - 1
+            } else {
+                missing_commits.len()
+            };
+
+            let mut text = Vec::new();
+            if !missing_commits.is_empty() {
+                text.push(Line::from(
+                    Span::from("These commits were removed without being dropped:")
+                        .style(Style::default().fg(Color::Yellow).bold()),
+                ));
+                text.extend(
+                    missing_commits[..shown_commits]
+                        .iter()
+                        .map(|commit| Line::from(format!("  {commit}"))),
+                );
+                if shown_commits < missing_commits.len() {
+                    text.push(Line::from(format!(
+                        "  and {} more",
+                        missing_commits.len() - shown_commits
+                    )));
+                }
+                text.push(Line::from(""));
+            }
+            if blocked {
+                text.push(Line::from("Restore them or drop them explicitly"));
+                text.push(Line::from("(rebase.missingCommitsCheck = error)."));
+            } else {
+                text.push(Line::from("Proceed to rebase?"));
+            }
 
-            let dialog_width = question.len() as u16 + 2 * (PADDING + 1);
-            let dialog_height = 4/*border + spacing A + question + spacing B*/ + rebase_confirmation.items().len() as u16 + 2/*spacing C + border*/;
+            let max_width = frame.size().width.saturating_sub(4);
+            let dialog_width = (text.iter().map(Line::width).max().unwrap_or(0) as u16
+                + 2 * (PADDING + 1))
+                .min(max_width);
+            let dialog_height = (3/*border + spacing A + spacing B*/ + text.len() as u16 + confirmation.items().len() as u16 + 2/*spacing C + border*/)
+                .min(max_height);
             let dialog_area = centered_rect(dialog_width, dialog_height, frame.size());
 
             frame.render_widget(Block::default().borders(Borders::ALL), dialog_area);
@@ -222,51 +692,57 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
             let dialog_inner_area = dialog_area.inner(&Margin::new(1, 1));
             frame.render_widget(Clear, dialog_inner_area);
 
-            let [question_area, confirmation_area] = {
+            let [text_area, confirmation_area] = {
                 let chunks = Layout::default()
                     .constraints([
-                        Constraint::Length(2 /*spacing A + question*/),
+                        Constraint::Length(1 /*spacing A*/ + text.len() as u16),
                         Constraint::Min(1),
                     ])
                     .split(dialog_inner_area);
                 [chunks[0], chunks[1]]
             };
 
-            let mut question_area = question_area.inner(&Margin {
+            let mut text_area = text_area.inner(&Margin {
                 horizontal: PADDING,
                 vertical: 0,
             });
-            question_area.y += 1;
-            frame.render_widget(Paragraph::new(question), question_area);
+            text_area.y += 1;
+            text_area.height = text_area.height.saturating_sub(1);
+            frame.render_widget(Paragraph::new(text), text_area);
 
             let confirmation_area = centered_rect(
-                2/*left padding + right padding*/ + rebase_confirmation
+                2/*left padding + right padding*/ + confirmation
                     .items()
                     .iter()
                     .map(|x| x.text().len())
                     .max()
                     .unwrap_or(0) as u16,
-                rebase_confirmation.items().len() as u16,
+                confirmation.items().len() as u16,
                 confirmation_area,
             );
-            let (confirmation, confirmation_state) = rebase_confirmation.widget_and_state();
+            app.screen_areas.confirmation = confirmation_area;
+            let (confirmation, confirmation_state) = confirmation.widget_and_state();
             let confirmation = confirmation
                 .highlight_style(Style::default().reversed())
                 .highlight_symbol(" " /*left padding*/);
             frame.render_stateful_widget(confirmation, confirmation_area, confirmation_state);
 
-            &[
-                (&["Y"], "quit and rebase"),
-                (&["N"], "quit and don't rebase"),
-                (&["ESC"], "don't quit"),
-            ]
+            if blocked {
+                fixed_hints(&[(&["N"], "quit and don't rebase"), (&["ESC"], "don't quit")])
+            } else {
+                fixed_hints(&[
+                    (&["Y"], "quit and rebase"),
+                    (&["N"], "quit and don't rebase"),
+                    (&["ESC"], "don't quit"),
+                ])
+            }
         }
     };
 
     let actions = footer_content.iter().map(|(keys, action)| {
-        interspace(*keys)
+        interspace(keys.iter())
             .map(|item| match item {
-                Some(key) => Span::from(*key).style(Style::default().bold()),
+                Some(key) => Span::from(key.clone()).style(Style::default().bold()),
                 None => Span::from("/"),
             })
             .chain(std::iter::once(Span::from(format!(": {action}"))))
@@ -281,6 +757,224 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
     frame.render_widget(footer, footer_area);
 }
 
+/// The footer hints for the actions, showing their current bindings. With several actions,
+/// only the first binding of each one is shown.
+fn action_hints(
+    keys: &Keys,
+    hints: &[(&[Action], &'static str)],
+) -> Vec<(Vec<String>, &'static str)> {
+    hints
+        .iter()
+        .filter_map(|(actions, description)| {
+            let bindings: Vec<_> = match actions {
+                [action] => keys.bindings(*action).iter().collect(),
+                _ => actions
+                    .iter()
+                    .filter_map(|action| keys.bindings(*action).first())
+                    .collect(),
+            };
+            (!bindings.is_empty()).then(|| {
+                let keys = bindings.iter().map(ToString::to_string).collect();
+                (keys, *description)
+            })
+        })
+        .collect()
+}
+
+fn fixed_hints(hints: &[(&[&str], &'static str)]) -> Vec<(Vec<String>, &'static str)> {
+    hints
+        .iter()
+        .map(|(keys, description)| (keys.iter().map(ToString::to_string).collect(), *description))
+        .collect()
+}
+
+fn popup_area(frame: &Frame) -> Rect {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(5),
+            Constraint::Min(1),
+            Constraint::Length(5),
+        ])
+        .split(frame.size());
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(7),
+            Constraint::Min(1),
+            Constraint::Length(7),
+        ])
+        .split(chunks[1])[1]
+}
+
+fn render_scrollable_popup(
+    frame: &mut Frame,
+    title: String,
+    lines: Vec<Line<'_>>,
+    scroll: &mut u16,
+) {
+    let popup_area = popup_area(frame);
+
+    let lines_count = lines.len();
+    let max_scroll = (lines_count as u16).saturating_sub(popup_area.height - 2 /*margins*/);
+    *scroll = std::cmp::min(*scroll, max_scroll);
+
+    let p = Paragraph::new(lines).scroll((*scroll, 0)).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::horizontal(1)),
+    );
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(p, popup_area);
+
+    let scrollbar_area = popup_area.inner(&Margin {
+        horizontal: 0,
+        vertical: 1,
+    });
+    if let Some(mut scrollbar_state) = scrollbar_state_from_offset(
+        lines_count,
+        scrollbar_area.height as usize,
+        *scroll as usize,
+    ) {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
+        frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
+    }
+}
+
+fn diff_lines(diff: &Diff) -> Vec<Line<'_>> {
+    let added = Style::default().fg(Color::Green);
+    let removed = Style::default().fg(Color::Red);
+    let header = Style::default().bold();
+    let hunk = Style::default().fg(Color::Cyan);
+
+    let mut in_patch = false;
+    diff.lines
+        .iter()
+        .map(|line| {
+            let line = line.as_str();
+            if line.starts_with("diff --git ") {
+                in_patch = true;
+            }
+
+            if !in_patch {
+                // Stat summary: color the "+++--" bar after the "|"
+                return match line.rsplit_once('|') {
+                    Some((file, bar)) => {
+                        let mut spans = vec![Span::from(file), Span::from("|")];
+                        spans.extend(bar.char_indices().map(|(i, char)| {
+                            let span = Span::from(&bar[i..i + char.len_utf8()]);
+                            match char {
+                                '+' => span.style(added),
+                                '-' => span.style(removed),
+                                _ => span,
+                            }
+                        }));
+                        Line::from(spans)
+                    }
+                    None => Line::from(line),
+                };
+            }
+
+            if line.starts_with("diff --git ")
+                || line.starts_with("index ")
+                || line.starts_with("--- ")
+                || line.starts_with("+++ ")
+                || line.starts_with("new file mode ")
+                || line.starts_with("deleted file mode ")
+            {
+                Line::from(Span::from(line).style(header))
+            } else if line.starts_with("@@") {
+                Line::from(Span::from(line).style(hunk))
+            } else if diff.word_diff {
+                Line::from(word_diff_spans(line, added, removed))
+            } else if line.starts_with('+') {
+                Line::from(Span::from(line).style(added))
+            } else if line.starts_with('-') {
+                Line::from(Span::from(line).style(removed))
+            } else {
+                Line::from(line)
+            }
+        })
+        .collect()
+}
+
+/// Splits a line from `--word-diff=plain` into spans, coloring the `[-removed-]` and
+/// `{+added+}` markers.
+fn word_diff_spans(mut line: &str, added: Style, removed: Style) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    loop {
+        let next_marker = [("[-", "-]", removed), ("{+", "+}", added)]
+            .into_iter()
+            .filter_map(|(open, close, style)| {
+                let start = line.find(open)?;
+                let end = start + line[start..].find(close)? + close.len();
+                Some((start, end, style))
+            })
+            .min_by_key(|(start, ..)| *start);
+
+        match next_marker {
+            Some((start, end, style)) => {
+                if start > 0 {
+                    spans.push(Span::from(&line[..start]));
+                }
+                spans.push(Span::from(&line[start..end]).style(style));
+                line = &line[end..];
+            }
+            None => {
+                if !line.is_empty() {
+                    spans.push(Span::from(line));
+                }
+                return spans;
+            }
+        }
+    }
+}
+
+fn commit_details_paragraph(details: Option<&CommitDetails>) -> Paragraph<'static> {
+    let block = Block::default()
+        .title(" Commit ")
+        .borders(Borders::ALL)
+        .padding(Padding::horizontal(1));
+
+    let Some(details) = details else {
+        return Paragraph::new("").block(block);
+    };
+
+    let field = |name: &'static str, value: &str| {
+        Line::from(vec![
+            Span::from(name).style(Style::default().bold()),
+            Span::from(value.to_string()),
+        ])
+    };
+
+    let mut lines = vec![
+        Line::from(
+            Span::from(format!("commit {}", details.hash))
+                .style(Style::default().fg(Color::Yellow)),
+        ),
+        field("Author:     ", &details.author),
+        field("AuthorDate: ", &details.author_date),
+        field("Commit:     ", &details.committer),
+        field("CommitDate: ", &details.commit_date),
+        Line::from(""),
+    ];
+    lines.extend(
+        details
+            .message
+            .lines()
+            .map(|line| Line::from(format!("    {line}"))),
+    );
+    lines.push(Line::from(""));
+    lines.extend(details.stat.iter().map(|line| Line::from(line.clone())));
+
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(block)
+}
+
 fn scrollbar_state_from_offset(
     content_length: usize,
     viewport_content_length: usize,
@@ -306,10 +1000,17 @@ fn scrollbar_position_from_offset(
 }
 
 fn max_command_len() -> usize {
-    COMMANDS.iter().map(|cmd| cmd.0.len()).max().unwrap_or(0)
+    COMMANDS
+        .iter()
+        .map(|cmd| cmd.label().len())
+        .max()
+        .unwrap_or(0)
 }
 
+/// The rectangle is clamped to the enclosing one.
 fn centered_rect(width: u16, height: u16, enclosing_rect: Rect) -> Rect {
+    let width = width.min(enclosing_rect.width);
+    let height = height.min(enclosing_rect.height);
     Rect {
         x: centered_pos(width, enclosing_rect.x, enclosing_rect.width),
         y: centered_pos(height, enclosing_rect.y, enclosing_rect.height),
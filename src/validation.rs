@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+
+use crate::todo::{TodoCommand, TodoLine};
+
+#[derive(Clone)]
+pub struct Problem {
+    pub index: usize,
+    pub message: String,
+    /// The line may still work, and does not block the rebase.
+    pub warning: bool,
+}
+
+/// Checks the todo list the way git will when it reads it back.
+pub fn validate(todo_lines: &[TodoLine]) -> Vec<Problem> {
+    let items: Vec<_> = todo_lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| line.item().map(|item| (index, &item.command)))
+        .collect();
+
+    let all_labels: HashSet<_> = items
+        .iter()
+        .filter_map(|(_, command)| match command {
+            TodoCommand::Label(label) => Some(label.trim()),
+            _ => None,
+        })
+        .collect();
+    // Labels can only be referred to after they are defined
+    let mut defined_labels = HashSet::new();
+
+    let mut problems = Vec::new();
+    let mut problem = |index: usize, message: String| {
+        problems.push(Problem {
+            index,
+            message,
+            warning: false,
+        })
+    };
+    // Pushed apart, as they may be warnings
+    let mut reference_problems = Vec::new();
+
+    let mut fixup_okay = false;
+    let mut update_refs = HashSet::new();
+    for &(index, command) in &items {
+        // Same rule as git: squash/fixup need some previous command that is not noop/drop
+        match command {
+            TodoCommand::Squash(_) | TodoCommand::Fixup(..) if !fixup_okay => problem(
+                index,
+                format!("cannot '{}' without a previous commit", command.name()),
+            ),
+            TodoCommand::Noop | TodoCommand::Drop(_) => {}
+            _ => fixup_okay = true,
+        }
+
+        if let Some(commit) = command.commit() {
+            if commit.hash.is_empty() {
+                problem(index, format!("missing commit for '{}'", command.name()));
+            }
+        }
+
+        match command {
+            TodoCommand::Exec(text) if text.trim().is_empty() => {
+                problem(index, "missing command for 'exec'".to_string())
+            }
+            TodoCommand::Label(label) if label.trim().is_empty() => {
+                problem(index, "missing label for 'label'".to_string())
+            }
+            TodoCommand::Label(label) => {
+                defined_labels.insert(label.trim());
+            }
+            TodoCommand::Reset { label, .. } => {
+                if label.trim().is_empty() {
+                    problem(index, "missing label for 'reset'".to_string());
+                }
+                reference_problems.extend(check_label_reference(
+                    label.trim(),
+                    &defined_labels,
+                    &all_labels,
+                    index,
+                ));
+            }
+            TodoCommand::Merge { label, .. } => {
+                // Octopus merges list several labels
+                if label.trim().is_empty() {
+                    problem(index, "missing label for 'merge'".to_string());
+                }
+                for label in label.split_whitespace() {
+                    reference_problems.extend(check_label_reference(
+                        label,
+                        &defined_labels,
+                        &all_labels,
+                        index,
+                    ));
+                }
+            }
+            TodoCommand::UpdateRef(reference) => {
+                if reference.trim().is_empty() {
+                    problem(index, "missing reference for 'update-ref'".to_string());
+                } else if !update_refs.insert(reference.trim()) {
+                    problem(
+                        index,
+                        format!("'{}' is updated more than once", reference.trim()),
+                    );
+                }
+            }
+            TodoCommand::Unknown {
+                command,
+                parameters,
+            } => match TodoCommand::parse(command, "") {
+                TodoCommand::Unknown { .. } => {
+                    problem(index, format!("unknown command '{command}'"))
+                }
+                _ => problem(
+                    index,
+                    format!("'{command}' does not accept arguments: '{parameters}'"),
+                ),
+            },
+            _ => {}
+        }
+    }
+
+    problems.extend(reference_problems);
+    problems.sort_by_key(|problem| problem.index);
+    problems
+}
+
+/// The commits from `original_lines` that are not in `todo_lines` anymore, not even as a `drop`.
+pub fn missing_commits(original_lines: &[TodoLine], todo_lines: &[TodoLine]) -> Vec<String> {
+    let current: Vec<_> = referenced_commits(todo_lines)
+        .map(|(hash, _)| hash)
+        .collect();
+    // The same commit may be written with hashes of different lengths
+    let is_present = |hash: &str| {
+        current
+            .iter()
+            .any(|other| hash.starts_with(other) || other.starts_with(hash))
+    };
+
+    let mut reported = HashSet::new();
+    referenced_commits(original_lines)
+        .filter(|(hash, _)| !is_present(hash) && reported.insert(*hash))
+        .map(|(hash, subject)| format!("{hash} {subject}").trim_end().to_string())
+        .collect()
+}
+
+/// The hashes and subjects of the commits picked, dropped or merged by the lines.
+fn referenced_commits(todo_lines: &[TodoLine]) -> impl Iterator<Item = (&str, &str)> {
+    todo_lines
+        .iter()
+        .filter_map(TodoLine::item)
+        .filter_map(|item| match &item.command {
+            TodoCommand::Merge {
+                commit: Some((_, hash)),
+                subject,
+                ..
+            } => Some((hash.as_str(), subject.as_deref().unwrap_or(""))),
+            command => command
+                .commit()
+                .map(|commit| (commit.hash.as_str(), commit.subject.as_str())),
+        })
+        .filter(|(hash, _)| !hash.is_empty())
+}
+
+/// Checks a reference against the labels defined before the line and in the whole list. Like
+/// git, a name that is not a label is taken as any revision, which is only a warning as it may
+/// well exist in the repository.
+fn check_label_reference(
+    label: &str,
+    defined_labels: &HashSet<&str>,
+    all_labels: &HashSet<&str>,
+    index: usize,
+) -> Option<Problem> {
+    if label.is_empty() || defined_labels.contains(label) || is_commit_reference(label) {
+        None
+    } else if all_labels.contains(label) {
+        Some(Problem {
+            index,
+            message: format!("label '{label}' is used before it is defined"),
+            warning: false,
+        })
+    } else {
+        Some(Problem {
+            index,
+            message: format!("'{label}' is not a label, it must be a revision"),
+            warning: true,
+        })
+    }
+}
+
+/// Besides labels, git accepts commit hashes, and `[new root]` for `reset`.
+fn is_commit_reference(label: &str) -> bool {
+    label == "[new root]"
+        || (label.len() >= 4 && label.len() <= 40 && label.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::parse_todo_list;
+
+    use super::{missing_commits, validate};
+
+    fn problems(lines: &[&str]) -> Vec<(usize, String)> {
+        validate(&parse_todo_list(lines, '#'))
+            .into_iter()
+            .map(|problem| {
+                let message = if problem.warning {
+                    format!("warning: {}", problem.message)
+                } else {
+                    problem.message
+                };
+                (problem.index, message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_list() {
+        let lines = [
+            "# comment",
+            "label onto",
+            "reset onto",
+            "pick 1a2b3c First",
+            "fixup 4d5e6f Fix",
+            "label topic",
+            "reset 0f0f0f0",
+            "reset topic # First",
+            "merge -C 7a8b9c topic # Merge",
+            "update-ref refs/heads/topic",
+            "exec make",
+        ];
+
+        assert!(problems(&lines).is_empty());
+    }
+
+    #[test]
+    fn invalid_list() {
+        let lines = [
+            "drop 0f0f0f Dropped",
+            "squash 1a2b3c First",
+            "pick",
+            "exec",
+            "reset nowhere",
+            "merge -C 7a8b9c",
+            "reset later",
+            "label later",
+            "merge -C 1a2b3c origin/topic later",
+            "update-ref refs/heads/topic",
+            "update-ref refs/heads/topic",
+            "frobnicate",
+            "break now",
+        ];
+
+        assert_eq!(
+            problems(&lines),
+            [
+                (1, "cannot 'squash' without a previous commit".to_string()),
+                (2, "missing commit for 'pick'".to_string()),
+                (3, "missing command for 'exec'".to_string()),
+                (
+                    4,
+                    "warning: 'nowhere' is not a label, it must be a revision".to_string()
+                ),
+                (5, "missing label for 'merge'".to_string()),
+                (6, "label 'later' is used before it is defined".to_string()),
+                (
+                    8,
+                    "warning: 'origin/topic' is not a label, it must be a revision".to_string()
+                ),
+                (
+                    10,
+                    "'refs/heads/topic' is updated more than once".to_string()
+                ),
+                (11, "unknown command 'frobnicate'".to_string()),
+                (12, "'break' does not accept arguments: 'now'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_commits_without_drop() {
+        let original = parse_todo_list(
+            &[
+                "pick 1a2b3c First",
+                "pick 4d5e6f Second",
+                "pick 7a8b9c Third",
+                "merge -C 0f0f0f topic # Merge topic",
+            ],
+            '#',
+        );
+        let current = parse_todo_list(&["drop 4d5e6f Second", "fixup 7a8b9c0d Third"], '#');
+
+        assert_eq!(
+            missing_commits(&original, &current),
+            ["1a2b3c First", "0f0f0f Merge topic"]
+        );
+        assert!(missing_commits(&original, &original).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{app::App, todo::TodoLine};
+
+/// A change to the list that `.` can repeat.
+#[derive(Clone)]
+enum Change {
+    MoveUp,
+    MoveDown,
+    SetCommand(&'static str),
+    Paste(Vec<TodoLine>),
+}
+
+#[derive(Default)]
+pub struct Vim {
+    /// The first key of a two-key sequence such as `gg`.
+    pending: Option<char>,
+    /// The lines yanked with `yy`, pasted by `P` until the next yank.
+    register: Option<Vec<TodoLine>>,
+    last_change: Option<Change>,
+}
+
+/// Handles the keys of the vim keymap in the main mode. Returns `false` for the keys it
+/// does not bind, which are then handled as in the default keymap.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    let pending = app.vim.pending.take();
+    let KeyCode::Char(char) = key.code else {
+        return false;
+    };
+    if !(key.modifiers - KeyModifiers::SHIFT).is_empty() {
+        return false;
+    }
+
+    // Without any line shown, there is nothing to act on, and a hidden line must not change
+    let empty = app.todo_list.shown_len() == 0;
+    match (pending, char) {
+        (Some('g'), 'g') if !empty => app.todo_list.select_first(),
+        (Some('y'), 'y') if !empty => app.vim.register = Some(app.selected_todo_lines()),
+        (Some('d'), 'd') if !empty => apply(app, Change::SetCommand("drop")),
+        (_, 'g' | 'y' | 'd') => app.vim.pending = Some(char),
+        (_, 'j') if !empty => app.todo_list.select_down(1),
+        (_, 'k') if !empty => app.todo_list.select_up(1),
+        (_, 'G') if !empty => app.todo_list.select_last(),
+        (_, 'V') if !empty => app.todo_list.toggle_range(),
+        (_, 'J') if !empty => apply(app, Change::MoveDown),
+        (_, 'K') if !empty => apply(app, Change::MoveUp),
+        (_, 'p') if !empty => apply(app, Change::SetCommand("pick")),
+        (_, 'P') if !empty => {
+            if let Some(lines) = app.vim.register.clone() {
+                apply(app, Change::Paste(lines));
+            }
+        }
+        (_, 'r') if !empty => apply(app, Change::SetCommand("reword")),
+        (_, 'e') if !empty => apply(app, Change::SetCommand("edit")),
+        (_, 's') if !empty => apply(app, Change::SetCommand("squash")),
+        (_, 'f') if !empty => apply(app, Change::SetCommand("fixup")),
+        (_, '.') if !empty => {
+            if let Some(change) = app.vim.last_change.clone() {
+                apply(app, change);
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn apply(app: &mut App, change: Change) {
+    match &change {
+        Change::MoveUp => app.move_todo_item_up(),
+        Change::MoveDown => app.move_todo_item_down(),
+        Change::SetCommand(name) => app.set_command(name),
+        Change::Paste(lines) => app.paste_todo_lines(lines.clone()),
+    }
+    app.vim.last_change = Some(change);
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::app::{test_app, App};
+
+    use super::handle_key;
+
+    fn press(app: &mut App, keys: &str) {
+        for char in keys.chars() {
+            handle_key(app, KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let todo_list = "pick 1a1a1a A\npick 2b2b2b B\n";
+        let mut app = test_app(todo_list);
+
+        // A single `d` waits for the second one
+        press(&mut app, "d");
+        assert_eq!(app.get_todo_list_string().unwrap(), todo_list);
+        press(&mut app, "d");
+        assert_eq!(
+            app.get_todo_list_string().unwrap(),
+            "drop 1a1a1a A\npick 2b2b2b B\n"
+        );
+
+        // The register is kept for several pastes
+        press(&mut app, "yyPP");
+        assert_eq!(
+            app.get_todo_list_string().unwrap(),
+            "drop 1a1a1a A\ndrop 1a1a1a A\ndrop 1a1a1a A\npick 2b2b2b B\n"
+        );
+
+        // Picking is still possible after a yank
+        press(&mut app, "p");
+        assert_eq!(
+            app.get_todo_list_string().unwrap(),
+            "drop 1a1a1a A\ndrop 1a1a1a A\npick 1a1a1a A\npick 2b2b2b B\n"
+        );
+
+        app.todo_list.items_mut().clear();
+        press(&mut app, "ddprefsyyP");
+        assert_eq!(app.get_todo_list_string().unwrap(), "");
+    }
+}
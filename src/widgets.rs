@@ -1,2 +1,3 @@
 pub mod selectable_list;
+pub mod text_area;
 pub mod text_input;
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeSet, marker::PhantomData};
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::{List, ListItem, ListState};
@@ -64,6 +64,13 @@ impl<T: Clone> IndexableMut for Vec<T> {
 pub struct SelectableList<'a, T> {
     items: T,
     list_state: ListState,
+    marks: BTreeSet<usize>,
+    /// Start of a range selection, which ends at the selected index.
+    anchor: Option<usize>,
+    /// The indices of the items that are shown, if some are hidden.
+    shown: Option<Vec<usize>>,
+    /// The state of the list of shown items, when some are hidden.
+    shown_state: ListState,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -72,6 +79,10 @@ impl<'a, T> SelectableList<'a, T> {
         SelectableList {
             items,
             list_state: ListState::default().with_selected(Some(0)),
+            marks: BTreeSet::new(),
+            anchor: None,
+            shown: None,
+            shown_state: ListState::default(),
             phantom: PhantomData,
         }
     }
@@ -88,6 +99,110 @@ impl<'a, T> SelectableList<'a, T> {
     pub fn select(&mut self, index: usize) {
         self.list_state.select(Some(index));
     }
+
+    /// The position in the rendered list of the first item shown when last rendered.
+    pub fn offset(&self) -> usize {
+        match self.shown {
+            Some(_) => self.shown_state.offset(),
+            None => self.list_state.offset(),
+        }
+    }
+
+    /// The index of the item at `position` in the rendered list.
+    pub fn index_at(&self, position: usize) -> Option<usize> {
+        match &self.shown {
+            Some(shown) => shown.get(position).copied(),
+            None => Some(position),
+        }
+    }
+
+    /// Hides the items not in `shown`, which must be in ascending order, or shows all of them
+    /// with `None`. Navigation skips the hidden items.
+    pub fn set_shown(&mut self, shown: Option<Vec<usize>>) {
+        if let Some(shown) = &shown {
+            if !shown.is_empty() && shown.binary_search(&self.selected()).is_err() {
+                let position = self.shown_position(shown).min(shown.len() - 1);
+                self.select(shown[position]);
+            }
+        }
+        self.shown = shown;
+    }
+
+    /// The number of items that are not hidden.
+    pub fn shown_len(&self) -> usize
+    where
+        T: Indexable,
+    {
+        match &self.shown {
+            Some(shown) => shown.len(),
+            None => self.items.len(),
+        }
+    }
+
+    /// The position of the selected item among the shown ones, or of the first shown item
+    /// after it.
+    fn shown_position(&self, shown: &[usize]) -> usize {
+        shown.partition_point(|index| *index < self.selected())
+    }
+
+    pub fn toggle_mark(&mut self) {
+        let selected = self.selected();
+        if !self.marks.remove(&selected) {
+            self.marks.insert(selected);
+        }
+    }
+
+    pub fn set_marks(&mut self, marks: impl IntoIterator<Item = usize>) {
+        self.marks = marks.into_iter().collect();
+    }
+
+    pub fn toggle_range(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => Some(self.selected()),
+        };
+    }
+
+    pub fn start_range(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = Some(self.selected());
+        }
+    }
+
+    pub fn has_multi_selection(&self) -> bool {
+        self.anchor.is_some() || !self.marks.is_empty()
+    }
+
+    pub fn clear_multi_selection(&mut self) {
+        self.anchor = None;
+        self.marks.clear();
+    }
+
+    /// The marked indices and the range selection, in ascending order, without the hidden
+    /// items. If there are none of them, only the selected index.
+    pub fn multi_selection(&self) -> Vec<usize> {
+        let mut selection = self.marks.clone();
+        if let Some(anchor) = self.anchor {
+            let selected = self.selected();
+            selection.extend(anchor.min(selected)..=anchor.max(selected));
+        }
+        if let Some(shown) = &self.shown {
+            selection.retain(|index| shown.binary_search(index).is_ok());
+        }
+
+        if selection.is_empty() {
+            vec![self.selected()]
+        } else {
+            selection.into_iter().collect()
+        }
+    }
+
+    /// Moves the selected index, the marks and the range selection to new indices.
+    pub fn remap_multi_selection(&mut self, map: impl Fn(usize) -> usize) {
+        self.select(map(self.selected()));
+        self.anchor = self.anchor.map(&map);
+        self.marks = self.marks.iter().copied().map(&map).collect();
+    }
 }
 
 impl<'a, T> SelectableList<'a, T>
@@ -103,33 +218,81 @@ where
         self.items.index(self.selected())
     }
 
-    pub fn widget_and_state(&mut self) -> (List, &mut ListState) {
+    pub fn widget_and_state(&mut self) -> (List<'_>, &mut ListState) {
         let widget = List::new(self.items.clone());
         let state = &mut self.list_state;
         (widget, state)
     }
 
+    pub fn widget_and_state_with(
+        &mut self,
+        list_item: impl Fn(usize, &<T as Indexable>::Item) -> ListItem<'a>,
+    ) -> (List<'a>, &mut ListState) {
+        match &self.shown {
+            Some(shown) => {
+                let items: Vec<_> = shown
+                    .iter()
+                    .map(|index| list_item(*index, self.items.index(*index)))
+                    .collect();
+                let selected = shown.binary_search(&self.selected()).ok();
+                self.shown_state.select(selected);
+                (List::new(items), &mut self.shown_state)
+            }
+            None => {
+                let items: Vec<_> = (0..self.items.len())
+                    .map(|index| list_item(index, self.items.index(index)))
+                    .collect();
+                (List::new(items), &mut self.list_state)
+            }
+        }
+    }
+
     pub fn select_up(&mut self, delta: usize) {
-        let selected = self.selected();
-        let selected = selected.saturating_sub(delta);
+        let selected = match &self.shown {
+            Some(shown) if shown.is_empty() => return,
+            Some(shown) => shown[self.shown_position(shown).saturating_sub(delta)],
+            None => self.selected().saturating_sub(delta),
+        };
         self.list_state.select(Some(selected));
     }
 
     pub fn select_down(&mut self, delta: usize) {
-        let len = self.items().len();
-        let selected = self.selected();
-        let selected = (selected + delta).min(len - 1);
+        let selected = match &self.shown {
+            Some(shown) if shown.is_empty() => return,
+            Some(shown) => {
+                let position = self.shown_position(shown);
+                // Not adding the delta if the selected item is hidden, as the position is
+                // already after it
+                let position = match shown.get(position) {
+                    Some(index) if *index == self.selected() => position + delta,
+                    _ => position + delta - 1,
+                };
+                shown[position.min(shown.len() - 1)]
+            }
+            None => (self.selected() + delta).min(self.items().len() - 1),
+        };
         self.list_state.select(Some(selected));
     }
 
+    pub fn select_first(&mut self) {
+        match &self.shown {
+            Some(shown) if shown.is_empty() => {}
+            Some(shown) => self.list_state.select(Some(shown[0])),
+            None => self.list_state.select(Some(0)),
+        }
+    }
+
     pub fn select_last(&mut self) {
-        let len = self.items().len();
-        self.list_state.select(Some(len - 1));
+        match &self.shown {
+            Some(shown) if shown.is_empty() => {}
+            Some(shown) => self.list_state.select(Some(shown[shown.len() - 1])),
+            None => self.list_state.select(Some(self.items().len() - 1)),
+        }
     }
 
     pub fn input(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Home => self.select(0),
+            KeyCode::Home => self.select_first(),
             KeyCode::End => self.select_last(),
             KeyCode::Up => self.select_up(1),
             KeyCode::Down => self.select_down(1),
@@ -0,0 +1,296 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Styled, Stylize},
+    widgets::{Block, StatefulWidget, Widget},
+};
+
+pub struct TextAreaState {
+    row_offset: usize,
+    column_offset: usize,
+    cursor_row: usize,
+    cursor_column: usize,
+}
+impl TextAreaState {
+    fn adjust(&mut self, lines: &[Vec<char>], width: usize, height: usize) {
+        // Fix cursor away from content end
+        self.cursor_row = self.cursor_row.min(lines.len() - 1);
+        self.cursor_column = self.cursor_column.min(lines[self.cursor_row].len());
+
+        self.row_offset = adjust_offset(self.row_offset, self.cursor_row, lines.len(), height);
+        self.column_offset = adjust_offset(
+            self.column_offset,
+            self.cursor_column,
+            // Room for the cursor after the end of the line
+            lines[self.cursor_row].len() + 1,
+            width,
+        );
+    }
+}
+
+/// The offset showing `cursor` in `size` cells, without leaving unused cells at the end if
+/// the content can fill them.
+fn adjust_offset(offset: usize, cursor: usize, len: usize, size: usize) -> usize {
+    let offset = offset.min(len.saturating_sub(size));
+    if cursor < offset {
+        cursor
+    } else if cursor - offset >= size {
+        cursor - size + 1
+    } else {
+        offset
+    }
+}
+
+/// A multi-line version of [`TextInput`](super::text_input::TextInput).
+pub struct TextArea {
+    lines: Vec<Vec<char>>,
+    state: TextAreaState,
+}
+impl TextArea {
+    /// The cursor starts at the end of the first line.
+    pub fn new(content: &str) -> Self {
+        let mut lines: Vec<Vec<char>> =
+            content.lines().map(|line| line.chars().collect()).collect();
+        if lines.is_empty() {
+            lines.push(Vec::new());
+        }
+        let cursor_column = lines[0].len();
+        TextArea {
+            lines,
+            state: TextAreaState {
+                row_offset: 0,
+                column_offset: 0,
+                cursor_row: 0,
+                cursor_column,
+            },
+        }
+    }
+
+    pub fn content(&self) -> String {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn widget_and_state(&mut self) -> (TextAreaWidget<'_>, &mut TextAreaState) {
+        let widget = TextAreaWidget {
+            lines: &self.lines,
+            block: None,
+            style: Style::default(),
+        };
+
+        (widget, &mut self.state)
+    }
+
+    fn line(&self) -> &Vec<char> {
+        &self.lines[self.state.cursor_row]
+    }
+
+    pub fn move_prev(&mut self) {
+        if self.state.cursor_column > 0 {
+            self.state.cursor_column -= 1;
+        } else if self.state.cursor_row > 0 {
+            self.state.cursor_row -= 1;
+            self.state.cursor_column = self.line().len();
+        }
+    }
+
+    pub fn move_next(&mut self) {
+        if self.state.cursor_column < self.line().len() {
+            self.state.cursor_column += 1;
+        } else if self.state.cursor_row + 1 < self.lines.len() {
+            self.state.cursor_row += 1;
+            self.state.cursor_column = 0;
+        }
+    }
+
+    pub fn move_up(&mut self, delta: usize) {
+        self.state.cursor_row = self.state.cursor_row.saturating_sub(delta);
+        self.state.cursor_column = self.state.cursor_column.min(self.line().len());
+    }
+
+    pub fn move_down(&mut self, delta: usize) {
+        self.state.cursor_row = (self.state.cursor_row + delta).min(self.lines.len() - 1);
+        self.state.cursor_column = self.state.cursor_column.min(self.line().len());
+    }
+
+    pub fn move_begin(&mut self) {
+        self.state.cursor_column = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.state.cursor_column = self.line().len();
+    }
+
+    pub fn insert(&mut self, char: char) {
+        let TextAreaState {
+            cursor_row,
+            cursor_column,
+            ..
+        } = self.state;
+        self.lines[cursor_row].insert(cursor_column, char);
+        self.state.cursor_column += 1;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let TextAreaState {
+            cursor_row,
+            cursor_column,
+            ..
+        } = self.state;
+        let rest = self.lines[cursor_row].split_off(cursor_column);
+        self.lines.insert(cursor_row + 1, rest);
+        self.state.cursor_row += 1;
+        self.state.cursor_column = 0;
+    }
+
+    pub fn delete(&mut self) {
+        let TextAreaState {
+            cursor_row,
+            cursor_column,
+            ..
+        } = self.state;
+        if cursor_column < self.line().len() {
+            self.lines[cursor_row].remove(cursor_column);
+        } else if cursor_row + 1 < self.lines.len() {
+            let next = self.lines.remove(cursor_row + 1);
+            self.lines[cursor_row].extend(next);
+        }
+    }
+
+    pub fn delete_prev(&mut self) {
+        if self.state.cursor_column > 0 || self.state.cursor_row > 0 {
+            self.move_prev();
+            self.delete();
+        }
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Left => self.move_prev(),
+            KeyCode::Right => self.move_next(),
+            KeyCode::Up => self.move_up(1),
+            KeyCode::Down => self.move_down(1),
+            KeyCode::Home => self.move_begin(),
+            KeyCode::End => self.move_end(),
+            KeyCode::Char(char) => self.insert(char),
+            KeyCode::Enter => self.insert_newline(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Backspace => self.delete_prev(),
+            _ => {}
+        }
+    }
+}
+
+pub struct TextAreaWidget<'a> {
+    lines: &'a [Vec<char>],
+    block: Option<Block<'a>>,
+    style: Style,
+}
+impl<'a> TextAreaWidget<'a> {
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+}
+impl<'a> Styled for TextAreaWidget<'a> {
+    type Item = TextAreaWidget<'a>;
+
+    fn style(&self) -> Style {
+        self.style
+    }
+
+    fn set_style(self, style: Style) -> Self::Item {
+        self.style(style)
+    }
+}
+impl<'a> StatefulWidget for TextAreaWidget<'a> {
+    type State = TextAreaState;
+
+    fn render(mut self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        buf.set_style(area, Style::reset());
+        buf.set_style(area, self.style);
+        let content_area = match self.block.take() {
+            Some(b) => {
+                let inner_area = b.inner(area);
+                b.render(area, buf);
+                inner_area
+            }
+            None => area,
+        };
+
+        if content_area.height < 1 || content_area.width < 1 {
+            return;
+        }
+
+        let (width, height) = (content_area.width as usize, content_area.height as usize);
+        state.adjust(self.lines, width, height);
+
+        // This assumes that each char occupies exactly one cell
+        for (y, line) in self
+            .lines
+            .iter()
+            .skip(state.row_offset)
+            .take(height)
+            .enumerate()
+        {
+            let string: String = line
+                .iter()
+                .skip(state.column_offset)
+                .chain(std::iter::repeat(&' '))
+                .take(width)
+                .collect();
+            buf.set_string(
+                content_area.x,
+                content_area.y + y as u16,
+                string,
+                Style::default(),
+            );
+        }
+        buf.get_mut(
+            content_area.x + (state.cursor_column - state.column_offset) as u16,
+            content_area.y + (state.cursor_row - state.row_offset) as u16,
+        )
+        .set_style(Style::default().reversed().slow_blink());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextArea;
+
+    #[test]
+    fn edition() {
+        let mut text = TextArea::new("Subject\n\nBody");
+        text.insert_newline();
+        assert_eq!(text.content(), "Subject\n\n\nBody");
+
+        text.move_down(2);
+        text.move_end();
+        text.insert('!');
+        assert_eq!(text.content(), "Subject\n\n\nBody!");
+
+        // Joining lines
+        text.move_begin();
+        text.delete_prev();
+        text.delete_prev();
+        assert_eq!(text.content(), "Subject\nBody!");
+        text.move_up(1);
+        text.move_end();
+        text.delete();
+        assert_eq!(text.content(), "SubjectBody!");
+
+        let mut text = TextArea::new("");
+        text.delete_prev();
+        text.delete();
+        assert_eq!(text.content(), "");
+    }
+}
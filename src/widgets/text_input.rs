@@ -57,7 +57,7 @@ impl TextInput {
         &self.content
     }
 
-    pub fn widget_and_state(&mut self) -> (TextInputWidget, &mut TextInputState) {
+    pub fn widget_and_state(&mut self) -> (TextInputWidget<'_>, &mut TextInputState) {
         let widget = TextInputWidget {
             content: &self.content,
             block: None,